@@ -0,0 +1,24 @@
+//! Interfaces every PoW algorithm of the node implements, kept apart from the algorithms so
+//! that none of them has to depend on another for them.
+
+use sc_consensus_pow::{Error as PowError, PowAlgorithm};
+use sp_consensus_pow::Seal as RawSeal;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use geopow_primitives::SealError;
+
+/// A [`PowAlgorithm`] that can tell why it rejects a seal. Implemented by every algorithm
+/// the node mines with, so that imports and `mining_submitWork` can name the reason.
+pub trait VerifyDetailed<B: BlockT>: PowAlgorithm<B> {
+	/// Verify a seal like [`PowAlgorithm::verify`], but tell why it was rejected. The outer
+	/// error is reserved for failing to verify at all, e.g. when the runtime cannot be
+	/// queried.
+	fn verify_detailed(
+		&self,
+		parent: &BlockId<B>,
+		pre_hash: &B::Hash,
+		pre_digest: Option<&[u8]>,
+		seal: &RawSeal,
+		difficulty: Self::Difficulty,
+	) -> Result<Result<(), SealError>, PowError<B>>;
+}
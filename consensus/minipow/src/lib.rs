@@ -1,43 +1,39 @@
-#![cfg_attr(not(feature = "std"), no_std)]
-
-use parity_scale_codec::{Decode, Encode};
+use geopow_consensus::{SealError, VerifyDetailed};
+use geopow_primitives::mini;
 use sc_consensus_pow::{PowAlgorithm, Error as PowError};
 use sp_consensus_pow::Seal as RawSeal;
 use sp_core::U256;
 use sp_runtime::{traits::Block as BlockT, generic::BlockId};
 
-/// A tiny PoW that uses a 64-bit checksum over (pre_hash || nonce).
-/// Work succeeds when sum(bytes) < target. Dev/demo only.
+pub use geopow_primitives::mini::Nonce;
+
+/// A tiny PoW for development chains. The work is the xxHash64 of `pre_hash || nonce`,
+/// which is cheap enough that a single CPU thread finds blocks at low difficulties, and it
+/// has to stay below `u64::MAX / difficulty`. A nonce is thus accepted with a probability
+/// of about `1 / difficulty`.
 #[derive(Clone)]
-pub struct MiniPow;
+pub struct MiniPow {
+    difficulty: U256,
+}
 
-// We accept the default worker's seal format: SCALE-encoded `U256` nonce.
-#[derive(Encode, Decode, Clone, Copy, Debug)]
-pub struct Nonce(U256);
+impl MiniPow {
+    /// Difficulty used when none is configured.
+    pub const DEFAULT_DIFFICULTY: u64 = 1_000_000;
 
-impl Nonce {
-    pub fn from_seal(seal: &RawSeal) -> Option<Self> {
-        let mut input = &seal[..];
-        U256::decode(&mut input).ok().map(Nonce)
+    /// MiniPow with a fixed difficulty. A difficulty of zero is treated as one.
+    pub fn new(difficulty: U256) -> Self {
+        Self { difficulty: difficulty.max(U256::one()) }
     }
-}
 
-fn checksum64<B: BlockT>(pre_hash: &B::Hash, nonce: U256) -> u64 {
-    let mut acc: u64 = 0;
-    for byte in pre_hash.as_ref() {
-        acc = acc.wrapping_add(*byte as u64);
+    /// Seal `pre_hash` with `nonce` if the resulting work meets `difficulty`.
+    pub fn seal(&self, pre_hash: &[u8], nonce: U256, difficulty: U256) -> Option<RawSeal> {
+        mini::seal(pre_hash, nonce, difficulty)
     }
-    let mut buf = [0u8; 32];
-    nonce.to_little_endian(&mut buf);
-    for byte in buf { acc = acc.wrapping_add(byte as u64); }
-    acc
 }
 
-fn target64(target: &U256) -> u64 {
-    if *target > U256::from(u64::MAX) {
-        u64::MAX
-    } else {
-        target.low_u64()
+impl Default for MiniPow {
+    fn default() -> Self {
+        Self::new(U256::from(Self::DEFAULT_DIFFICULTY))
     }
 }
 
@@ -47,30 +43,117 @@ where
 {
     type Difficulty = U256;
 
-    /// Return the current target (easy for dev).
+    /// Return the configured difficulty.
     fn difficulty(
         &self,
         _parent: B::Hash,
     ) -> Result<Self::Difficulty, PowError<B>> {
-        // Very permissive by default: u64::MAX/1024 as a U256.
-        Ok(U256::from(u64::MAX / 1024))
+        Ok(self.difficulty)
     }
 
-    /// Verify a seal: (parent, pre_hash, digest, seal, target)
+    /// Verify a seal: (parent, pre_hash, digest, seal, difficulty)
     fn verify(
+        &self,
+        parent: &BlockId<B>,
+        pre_hash: &B::Hash,
+        pre_digest: Option<&[u8]>,
+        seal: &RawSeal,
+        difficulty: Self::Difficulty,
+    ) -> Result<bool, PowError<B>> {
+        Ok(self.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty)?.is_ok())
+    }
+}
+
+impl<B> VerifyDetailed<B> for MiniPow
+where
+    B: BlockT,
+{
+    fn verify_detailed(
         &self,
         _parent: &BlockId<B>,
         pre_hash: &B::Hash,
         _pre_digest: Option<&[u8]>,
         seal: &RawSeal,
-        target: Self::Difficulty,
-    ) -> Result<bool, PowError<B>> {
-        // If the seal cannot be parsed, consider verification failed.
-        let Nonce(n) = match Nonce::from_seal(seal) {
-            Some(n) => n,
-            None => return Ok(false),
-        };
-        let work = checksum64::<B>(pre_hash, n);
-        Ok(work < target64(&target))
+        difficulty: Self::Difficulty,
+    ) -> Result<Result<(), SealError>, PowError<B>> {
+        Ok(mini::check_seal(pre_hash.as_ref(), seal, difficulty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geopow_primitives::difficulty::target;
+    use parity_scale_codec::Encode;
+    use sp_core::H256;
+    use sp_runtime::{generic, testing::Header, OpaqueExtrinsic};
+
+    type Block = generic::Block<Header, OpaqueExtrinsic>;
+
+    /// Fraction of the first `trials` nonces accepted at `difficulty`.
+    fn acceptance_rate(difficulty: u64, trials: u64) -> f64 {
+        let pow = MiniPow::new(U256::from(difficulty));
+        let pre_hash = H256::repeat_byte(0x42);
+        let accepted = (0..trials)
+            .filter(|nonce| {
+                pow.seal(pre_hash.as_bytes(), U256::from(*nonce), U256::from(difficulty)).is_some()
+            })
+            .count();
+        accepted as f64 / trials as f64
+    }
+
+    #[test]
+    fn difficulty_one_accepts_every_nonce() {
+        assert_eq!(acceptance_rate(1, 1_000), 1.0);
+        assert_eq!(target(U256::zero()), u64::MAX);
+    }
+
+    #[test]
+    fn acceptance_rate_is_inverse_of_difficulty() {
+        for difficulty in [2, 16, 256] {
+            let rate = acceptance_rate(difficulty, 100_000);
+            let expected = 1.0 / difficulty as f64;
+            assert!(
+                (rate - expected).abs() < expected * 0.15,
+                "difficulty {} accepted {} of nonces",
+                difficulty,
+                rate,
+            );
+        }
+    }
+
+    #[test]
+    fn huge_difficulties_accept_practically_nothing() {
+        assert_eq!(acceptance_rate(u64::MAX, 10_000), 0.0);
+        assert_eq!(target(U256::MAX), 0);
+    }
+
+    #[test]
+    fn verify_agrees_with_seal() {
+        let pow = MiniPow::new(U256::from(64));
+        let parent = BlockId::<Block>::hash(H256::zero());
+        let pre_hash = H256::repeat_byte(7);
+        let difficulty = PowAlgorithm::<Block>::difficulty(&pow, H256::zero()).unwrap();
+        assert_eq!(difficulty, U256::from(64));
+
+        let seal = (0u64..)
+            .find_map(|nonce| pow.seal(pre_hash.as_bytes(), U256::from(nonce), difficulty))
+            .unwrap();
+        assert!(pow.verify(&parent, &pre_hash, None, &seal, difficulty).unwrap());
+
+        let rejected = (0u64..)
+            .find(|nonce| pow.seal(pre_hash.as_bytes(), U256::from(*nonce), difficulty).is_none())
+            .unwrap();
+        let seal = U256::from(rejected).encode();
+        assert!(!pow.verify(&parent, &pre_hash, None, &seal, difficulty).unwrap());
+        assert_eq!(
+            pow.verify_detailed(&parent, &pre_hash, None, &seal, difficulty).unwrap(),
+            Err(SealError::InsufficientWork),
+        );
+        assert!(!pow.verify(&parent, &pre_hash, None, &vec![1, 2, 3], difficulty).unwrap());
+        assert_eq!(
+            pow.verify_detailed(&parent, &pre_hash, None, &vec![1, 2, 3], difficulty).unwrap(),
+            Err(SealError::BadSeal),
+        );
     }
 }
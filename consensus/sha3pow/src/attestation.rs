@@ -0,0 +1,160 @@
+//! Checking proof-of-location attestations against the registered witnesses.
+//!
+//! A witness measures the round-trip time to a miner's claimed IP address and signs it.
+//! Signals cannot travel faster than light in fibre, so the measurement bounds the
+//! distance between the witness and the miner. A location claim backed by enough
+//! witnesses is therefore hard to fake without the cooperation of the witnesses.
+
+use crate::{ AttestationContext, GeoPoint, PreDigest };
+use geopow_primitives::Witness;
+use sp_core::{ crypto::AccountId32, sr25519, Pair };
+use std::collections::BTreeSet;
+
+pub use geopow_primitives::attestation::{
+	distance_km, max_distance_km, Attestation, KM_PER_RTT_MICROSECOND,
+};
+
+/// Whether `attestation` from `witness` supports a miner with the given account and IP being
+/// at `location`, under the rules of `context`.
+fn supports(
+	attestation: &Attestation,
+	witness: &Witness,
+	miner: &AccountId32,
+	ip: &[u8],
+	location: GeoPoint,
+	context: &AttestationContext<AccountId32>,
+) -> bool {
+	let fresh = attestation.at <= context.block_number &&
+		context.block_number - attestation.at <= context.max_age;
+	let payload = Attestation::payload(
+		&attestation.witness,
+		miner,
+		ip,
+		attestation.rtt_micros,
+		attestation.at,
+	);
+
+	fresh &&
+		sr25519::Pair::verify(&attestation.signature, &payload, &witness.key) &&
+		witness.location.distance_km(&location) <= max_distance_km(attestation.rtt_micros)
+}
+
+/// Number of distinct registered witnesses whose attestations in `pre_digest` support the
+/// miner being at `location`. Attestations from unknown witnesses, with bad signatures,
+/// expired or too slow to reach `location` are ignored. A pre-digest without an author has
+/// no valid attestations.
+pub fn attesting_witnesses(
+	pre_digest: &PreDigest,
+	location: GeoPoint,
+	context: &AttestationContext<AccountId32>,
+) -> usize {
+	let miner = match &pre_digest.author {
+		Some(author) => author,
+		None => return 0,
+	};
+
+	pre_digest
+		.attestations
+		.iter()
+		.filter(|attestation| {
+			context
+				.witnesses
+				.iter()
+				.find(|(account, _)| *account == attestation.witness)
+				.map_or(false, |(_, witness)| {
+					let ip = &pre_digest.location.ip;
+					supports(attestation, witness, miner, ip, location, context)
+				})
+		})
+		.map(|attestation| &attestation.witness)
+		.collect::<BTreeSet<_>>()
+		.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::LocationClaim;
+
+	const BERLIN: GeoPoint = GeoPoint::new(52_520_000, 13_400_000);
+	const PARIS: GeoPoint = GeoPoint::new(48_860_000, 2_350_000);
+
+	fn witness_pair(seed: u8) -> sr25519::Pair {
+		sr25519::Pair::from_seed(&[seed; 32])
+	}
+
+	fn witness_at(seed: u8, location: GeoPoint) -> (AccountId32, Witness) {
+		let witness = Witness { key: witness_pair(seed).public(), location };
+		(AccountId32::new([seed; 32]), witness)
+	}
+
+	fn context() -> AttestationContext<AccountId32> {
+		AttestationContext {
+			witnesses: vec![witness_at(1, BERLIN), witness_at(2, PARIS)],
+			min_witnesses: 2,
+			max_age: 10,
+			block_number: 100,
+		}
+	}
+
+	fn miner() -> AccountId32 {
+		AccountId32::new([42; 32])
+	}
+
+	fn attest(seed: u8, rtt_micros: u32, at: u32) -> Attestation {
+		Attestation::sign(
+			&witness_pair(seed),
+			AccountId32::new([seed; 32]),
+			&miner(),
+			b"203.0.113.7",
+			rtt_micros,
+			at,
+		)
+	}
+
+	fn pre_digest(attestations: Vec<Attestation>) -> PreDigest {
+		PreDigest {
+			author: Some(miner()),
+			location: LocationClaim::new("203.0.113.7", None),
+			attestations,
+		}
+	}
+
+	#[test]
+	fn counts_distinct_supporting_witnesses() {
+		// A miner in Berlin, 1 ms from the local witness and 10 ms from the one in Paris.
+		let attestations = vec![attest(1, 1_000, 95), attest(2, 10_000, 99), attest(1, 900, 96)];
+		assert_eq!(attesting_witnesses(&pre_digest(attestations), BERLIN, &context()), 2);
+	}
+
+	#[test]
+	fn ignores_attestations_that_do_not_hold() {
+		let context = context();
+		let count =
+			|attestation| attesting_witnesses(&pre_digest(vec![attestation]), BERLIN, &context);
+
+		// Paris is ~880 km away, which takes longer than 5 ms to reach and come back.
+		assert_eq!(count(attest(2, 5_000, 99)), 0);
+		// Expired, and from the future.
+		assert_eq!(count(attest(1, 1_000, 89)), 0);
+		assert_eq!(count(attest(1, 1_000, 101)), 0);
+		// Unknown witness.
+		assert_eq!(count(attest(3, 1_000, 99)), 0);
+		// Tampered with after signing.
+		let mut forged = attest(1, 1_000, 99);
+		forged.rtt_micros = 2_000;
+		assert_eq!(count(forged), 0);
+	}
+
+	#[test]
+	fn attestations_are_bound_to_the_claim() {
+		let context = context();
+		let mut other_ip = pre_digest(vec![attest(1, 1_000, 99)]);
+		other_ip.location = LocationClaim::new("198.51.100.1", None);
+		assert_eq!(attesting_witnesses(&other_ip, BERLIN, &context), 0);
+
+		let mut anonymous = pre_digest(vec![attest(1, 1_000, 99)]);
+		anonymous.author = None;
+		assert_eq!(attesting_witnesses(&anonymous, BERLIN, &context), 0);
+	}
+}
@@ -1,63 +1,124 @@
-use parity_scale_codec::{ Decode, Encode };
+use parity_scale_codec::Decode;
 // use consensus_geo_pow::{ Error, PowAlgorithm };
-use sha3::{ Digest, Sha3_256 };
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ ApiExt, ProvideRuntimeApi };
+use sp_blockchain::HeaderBackend;
 use sp_consensus_pow::{ DifficultyApi, Seal as RawSeal};
+use geopow_consensus::VerifyDetailed;
 use sc_consensus_pow::{ Error, PowAlgorithm };
-use sp_core::{ H256, U256 };
+use sp_core::{ crypto::AccountId32, H256, U256 };
 use sp_runtime::generic::BlockId;
-use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::{ Block as BlockT, UniqueSaturatedInto };
 use std::sync::Arc;
-mod geo;
+pub mod attestation;
+pub mod geo;
+pub mod locator;
 
-/// Determine whether the given hash satisfies the given difficulty.
-/// The test is done by multiplying the two together. If the product
-/// overflows the bounds of U256, then the product (and thus the hash)
-/// was too high.
-pub fn hash_meets_difficulty(hash: &H256, difficulty: U256) -> bool {
-	let num_hash = U256::from(&hash[..]);
-	let (_, overflowed) = num_hash.overflowing_mul(difficulty);
+pub use geopow_primitives::{
+	check_seal, hash_meets_difficulty, Attestation, AttestationContext, Compute, GeoPoint,
+	LocationClaim, MiningZoneParams, PreDigest, RegionId, Seal, SealError, ZoneBlock, ZoneMode,
+};
+pub use geopow_runtime_api::{ GeoMiningApi, GeoQuotasApi, LocationWitnessApi };
+pub use locator::{ CsvRangeLocator, GeoError, GeoLocator, StubLocator };
 
-	!overflowed
+pub fn node_is_on_mining_zone(
+	block: &ZoneBlock,
+	locator: &dyn GeoLocator,
+	ip: &str,
+	params: &MiningZoneParams,
+) -> bool {
+	log::info!("Seed: {:?}, block: {}, IP: {:?}", block.parent, block.number, ip);
+	return geo::node_is_on_mining_zone(block, locator, ip, params);
 }
 
-pub fn node_is_on_mining_zone(hash: &H256, ip: &str) -> bool {
-	log::info!("Hash: {:?}, IP: {:?}", hash, ip);
-	return geo::node_is_on_mining_zone(hash, ip);
+/// The hash reseeded mining zones of a block are seeded with: that of its parent. Every
+/// node knows it before the block is mined, and unlike the pre-hash, miners cannot grind it.
+pub fn zone_seed<B: BlockT<Hash = H256>>(parent: &BlockId<B>) -> Result<H256, Error<B>> {
+	match parent {
+		BlockId::Hash(hash) => Ok(*hash),
+		BlockId::Number(number) => Err(Error::Environment(format!(
+			"Mining zone is seeded with the parent hash, got block number {}",
+			number
+		))),
+	}
 }
 
-/// A Seal struct that will be encoded to a Vec<u8> as used as the
-/// `RawSeal` type.
-#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
-pub struct Seal {
-	pub difficulty: U256,
-	pub work: H256,
-	pub nonce: U256,
+/// Everything the mining zone of a block built on top of `parent` is derived from.
+pub fn zone_block<B, C>(client: &C, parent: &BlockId<B>) -> Result<ZoneBlock, Error<B>>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	let parent_hash = zone_seed(parent)?;
+	let number = client
+		.number(parent_hash)
+		.map_err(|err| {
+			Error::Environment(format!("Fetching parent block number failed: {:?}", err))
+		})?
+		.ok_or_else(|| Error::Environment(format!("Unknown parent block {}", parent_hash)))?;
+	Ok(ZoneBlock {
+		genesis: client.info().genesis_hash,
+		parent: parent_hash,
+		number: UniqueSaturatedInto::<u64>::unique_saturated_into(number) + 1,
+	})
 }
 
-/// A not-yet-computed attempt to solve the proof of work. Calling the
-/// compute method will compute the hash and return the seal.
-#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
-pub struct Compute {
-	pub difficulty: U256,
-	pub pre_hash: H256,
-	pub nonce: U256,
-}
+/// Check that the block's pre-digest carries a location claim that lies inside
+/// the mining zone of `block` shaped by `params`. A missing or undecodable
+/// pre-digest, or an address the locator cannot resolve, fails.
+pub fn pre_digest_is_on_mining_zone(
+	block: &ZoneBlock,
+	pre_digest: Option<&[u8]>,
+	locator: &dyn GeoLocator,
+	params: &MiningZoneParams,
+) -> bool {
+	let pre_digest = match pre_digest.map(|mut raw| PreDigest::decode(&mut raw)) {
+		Some(Ok(pre_digest)) => pre_digest,
+		_ => return false,
+	};
 
-impl Compute {
-	pub fn compute(self) -> Seal {
-		let work = H256::from_slice(Sha3_256::digest(&self.encode()[..]).as_slice());
+	node_is_on_mining_zone(block, locator, &pre_digest.location.ip(), params)
+}
 
-		Seal {
-			nonce: self.nonce,
-			difficulty: self.difficulty,
-			work,
-		}
+/// Check that enough distinct witnesses attest to the location claimed in the block's
+/// pre-digest, as resolved by `locator`. Always passes if `context` requires no witnesses.
+pub fn pre_digest_is_attested(
+	pre_digest: Option<&[u8]>,
+	locator: &dyn GeoLocator,
+	context: &AttestationContext<AccountId32>,
+) -> bool {
+	if context.min_witnesses == 0 {
+		return true;
 	}
+
+	let pre_digest = match pre_digest.map(|mut raw| PreDigest::decode(&mut raw)) {
+		Some(Ok(pre_digest)) => pre_digest,
+		_ => return false,
+	};
+	let location = match locator.locate_str(&pre_digest.location.ip()) {
+		Ok(location) => location,
+		Err(_) => return false,
+	};
+
+	let witnesses = attestation::attesting_witnesses(&pre_digest, location, context);
+	log::debug!("Location attested by {} of {} witnesses", witnesses, context.min_witnesses);
+	witnesses >= context.min_witnesses as usize
 }
 
+/// The location claimed in the block's pre-digest and where the claimed IP address is, as
+/// resolved by `locator`.
+pub fn locate_claim(
+	pre_digest: Option<&[u8]>,
+	locator: &dyn GeoLocator,
+) -> Option<(LocationClaim, GeoPoint)> {
+	let pre_digest = PreDigest::decode(&mut pre_digest?).ok()?;
+	let location = locator.locate_str(&pre_digest.location.ip()).ok()?;
+	Some((pre_digest.location, location))
+}
+
+
 /// A minimal PoW algorithm that uses Sha3 hashing.
-/// Difficulty is fixed at 1_000_000
+/// Difficulty is fixed at 1_000_000, and miners are located with the [`StubLocator`]
+/// inside a zone with the default [`MiningZoneParams`].
 #[derive(Clone)]
 pub struct MinimalSha3Algorithm;
 
@@ -72,48 +133,37 @@ impl<B: BlockT<Hash = H256>> PowAlgorithm<B> for MinimalSha3Algorithm {
 
 	fn verify(
 		&self,
-		_parent: &BlockId<B>,
+		parent: &BlockId<B>,
 		pre_hash: &H256,
-		_pre_digest: Option<&[u8]>,
+		pre_digest: Option<&[u8]>,
 		seal: &RawSeal,
 		difficulty: Self::Difficulty
-		// ip: &str
 	) -> Result<bool, Error<B>> {
-		log::info!("VERIFYING");
-
-		// TODO: Use IP from the block
-		// let ip = "0.0.0.0";
-		// See whether the node meets the location requirement. If not, fail fast.
-		// if !geo::node_is_on_mining_zone(pre_hash, ip) {
-		// 	return Ok(false);
-		// }
-		log::info!("PRE SEAL");
-
-		// Try to construct a seal object by decoding the raw seal given
-		let seal = match Seal::decode(&mut &seal[..]) {
-			Ok(seal) => seal,
-			Err(_) => {
-				return Ok(false);
-			}
-		};
-
-		// See whether the hash meets the difficulty requirement. If not, fail fast.
-		if !hash_meets_difficulty(&seal.work, difficulty) {
-			return Ok(false);
-		}
+		Ok(self.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty)?.is_ok())
+	}
+}
 
-		// Make sure the provided work actually comes from the correct pre_hash
-		let compute = Compute {
-			difficulty,
-			pre_hash: *pre_hash,
-			nonce: seal.nonce,
-		};
+impl<B: BlockT<Hash = H256>> VerifyDetailed<B> for MinimalSha3Algorithm {
+	fn verify_detailed(
+		&self,
+		parent: &BlockId<B>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &RawSeal,
+		difficulty: Self::Difficulty
+	) -> Result<Result<(), SealError>, Error<B>> {
+		log::info!("VERIFYING");
 
-		if compute.compute() != seal {
-			return Ok(false);
+		// See whether the miner meets the location requirement. If not, fail fast. The
+		// default zone is reseeded every block, so only the parent hash matters.
+		let params = MiningZoneParams::default();
+		let block = ZoneBlock { genesis: H256::zero(), parent: zone_seed(parent)?, number: 0 };
+		if !pre_digest_is_on_mining_zone(&block, pre_digest, &StubLocator, &params) {
+			return Ok(Err(SealError::OutsideZone));
 		}
+		log::info!("PRE SEAL");
 
-		Ok(true)
+		Ok(check_seal(pre_hash, seal, difficulty))
 	}
 }
 
@@ -121,11 +171,53 @@ impl<B: BlockT<Hash = H256>> PowAlgorithm<B> for MinimalSha3Algorithm {
 /// Needs a reference to the client so it can grab the difficulty from the runtime.
 pub struct Sha3Algorithm<C> {
 	client: Arc<C>,
+	mining_zone: Option<Arc<dyn GeoLocator>>,
 }
 
 impl<C> Sha3Algorithm<C> {
+	/// Plain Sha3 PoW: blocks may be mined from anywhere.
 	pub fn new(client: Arc<C>) -> Self {
-		Self { client }
+		Self { client, mining_zone: None }
+	}
+
+	/// Sha3 PoW that only accepts blocks whose pre-digest places the miner inside
+	/// the mining zone. Claimed addresses are resolved with `locator`, the zone
+	/// parameters and witness rules are read from the runtime at the parent block.
+	pub fn with_mining_zone(client: Arc<C>, locator: Arc<dyn GeoLocator>) -> Self {
+		Self { client, mining_zone: Some(locator) }
+	}
+
+	/// The locator used to enforce the mining zone, if the zone is enforced at all.
+	pub fn locator(&self) -> Option<&Arc<dyn GeoLocator>> {
+		self.mining_zone.as_ref()
+	}
+}
+
+impl<C> Sha3Algorithm<C> {
+	/// Whether a miner at `location` may seal a block on top of `parent`, as decided by the
+	/// runtime at `parent`. Runtimes predating [`GeoMiningApi::is_eligible`] only provide
+	/// the zone parameters, the node derives the zone itself for their blocks.
+	fn is_eligible<B>(&self, parent: &BlockId<B>, location: GeoPoint) -> Result<bool, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+		C::Api: GeoMiningApi<B>,
+	{
+		let runtime_error = |err: sp_api::ApiError| {
+			sc_consensus_pow::Error::Environment(
+				format!("Checking mining zone eligibility in runtime failed: {:?}", err)
+			)
+		};
+		let api = self.client.runtime_api();
+		let runtime_decides = api
+			.has_api_with::<dyn GeoMiningApi<B>, _>(parent, |version| version >= 2)
+			.map_err(runtime_error)?;
+		if runtime_decides {
+			return api.is_eligible(parent, zone_seed(parent)?, location).map_err(runtime_error);
+		}
+
+		let params = api.zone_params(parent).map_err(runtime_error)?;
+		Ok(geo::point_is_in_zone(&zone_block(self.client.as_ref(), parent)?, &params, location))
 	}
 }
 
@@ -133,14 +225,19 @@ impl<C> Sha3Algorithm<C> {
 // it'll derive impl<C: Clone> Clone for Sha3Algorithm<C>. But C in practice isn't Clone.
 impl<C> Clone for Sha3Algorithm<C> {
 	fn clone(&self) -> Self {
-		Self::new(self.client.clone())
+		Self { client: self.client.clone(), mining_zone: self.mining_zone.clone() }
 	}
 }
 
 // Here we implement the general PowAlgorithm trait for our concrete Sha3Algorithm
 impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B>
 	for Sha3Algorithm<C>
-	where C: ProvideRuntimeApi<B>, C::Api: DifficultyApi<B, U256>
+	where
+		C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+		C::Api: DifficultyApi<B, U256>
+			+ GeoMiningApi<B>
+			+ LocationWitnessApi<B, AccountId32>
+			+ GeoQuotasApi<B>,
 {
 	type Difficulty = U256;
 
@@ -158,36 +255,91 @@ impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B>
 
 	fn verify(
 		&self,
-		_parent: &BlockId<B>,
+		parent: &BlockId<B>,
 		pre_hash: &H256,
-		_pre_digest: Option<&[u8]>,
+		pre_digest: Option<&[u8]>,
 		seal: &RawSeal,
 		difficulty: Self::Difficulty
 	) -> Result<bool, Error<B>> {
-		// Try to construct a seal object by decoding the raw seal given
-		let seal = match Seal::decode(&mut &seal[..]) {
-			Ok(seal) => seal,
-			Err(_) => {
-				return Ok(false);
-			}
-		};
+		Ok(self.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty)?.is_ok())
+	}
+}
 
-		// See whether the hash meets the difficulty requirement. If not, fail fast.
-		if !hash_meets_difficulty(&seal.work, difficulty) {
-			return Ok(false);
-		}
+impl<B: BlockT<Hash = H256>, C> VerifyDetailed<B>
+	for Sha3Algorithm<C>
+	where
+		C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+		C::Api: DifficultyApi<B, U256>
+			+ GeoMiningApi<B>
+			+ LocationWitnessApi<B, AccountId32>
+			+ GeoQuotasApi<B>,
+{
+	fn verify_detailed(
+		&self,
+		parent: &BlockId<B>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &RawSeal,
+		difficulty: Self::Difficulty
+	) -> Result<Result<(), SealError>, Error<B>> {
+		// See whether the miner meets the location requirement. If not, fail fast.
+		if let Some(locator) = &self.mining_zone {
+			let (claim, location) = match locate_claim(pre_digest, locator.as_ref()) {
+				Some(located) => located,
+				None => return Ok(Err(SealError::OutsideZone)),
+			};
+			if !self.is_eligible(parent, location)? {
+				return Ok(Err(SealError::OutsideZone));
+			}
 
-		// Make sure the provided work actually comes from the correct pre_hash
-		let compute = Compute {
-			difficulty,
-			pre_hash: *pre_hash,
-			nonce: seal.nonce,
-		};
+			let context = self.client
+				.runtime_api()
+				.attestation_context(parent)
+				.map_err(|err| {
+					sc_consensus_pow::Error::Environment(
+						format!("Fetching location witnesses from runtime failed: {:?}", err)
+					)
+				})?;
+			if !pre_digest_is_attested(pre_digest, locator.as_ref(), &context) {
+				return Ok(Err(SealError::NotAttested));
+			}
 
-		if compute.compute() != seal {
-			return Ok(false);
+			// The block counts against the region of the point it claims, so that has to be
+			// the region the miner is actually in, and that region must have quota left.
+			let region_at = |location| {
+				self.client
+					.runtime_api()
+					.region_at(parent, location)
+					.map_err(|err| {
+						sc_consensus_pow::Error::Environment(
+							format!("Fetching quota region from runtime failed: {:?}", err)
+						)
+					})
+			};
+			let region = region_at(location)?;
+			let claimed = match claim.point {
+				Some(point) => region_at(point)?,
+				None => None,
+			};
+			if claimed != region {
+				return Ok(Err(SealError::RegionMismatch));
+			}
+			if let Some(region) = region {
+				let has_quota = self.client
+					.runtime_api()
+					.has_quota(parent, region)
+					.map_err(|err| {
+						sc_consensus_pow::Error::Environment(
+							format!("Fetching region quota from runtime failed: {:?}", err)
+						)
+					})?;
+				if !has_quota {
+					return Ok(Err(SealError::QuotaExhausted));
+				}
+			}
 		}
 
-		Ok(true)
+		Ok(check_seal(pre_hash, seal, difficulty))
 	}
-}
\ No newline at end of file
+}
+
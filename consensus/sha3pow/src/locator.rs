@@ -0,0 +1,254 @@
+//! Mapping of miner IP addresses to coordinates.
+
+use geopow_primitives::GeoPoint;
+use sp_core::hashing::blake2_256;
+use std::{
+	fmt,
+	fs::File,
+	io::{ BufRead, BufReader },
+	net::IpAddr,
+	path::Path,
+};
+
+/// Reasons an IP address could not be located.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GeoError {
+	/// The input is not a valid IPv4 or IPv6 address.
+	InvalidAddress(String),
+	/// The database has no entry covering the address.
+	NotFound(IpAddr),
+	/// The database could not be read or is malformed.
+	Database(String),
+}
+
+impl fmt::Display for GeoError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			GeoError::InvalidAddress(ip) => write!(f, "invalid IP address `{}`", ip),
+			GeoError::NotFound(ip) => write!(f, "no location known for {}", ip),
+			GeoError::Database(err) => write!(f, "GeoIP database error: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for GeoError {}
+
+/// Resolves IP addresses to points on the globe.
+pub trait GeoLocator: Send + Sync {
+	fn locate(&self, ip: IpAddr) -> Result<GeoPoint, GeoError>;
+
+	/// Parse a textual IPv4 or IPv6 address and locate it.
+	fn locate_str(&self, ip: &str) -> Result<GeoPoint, GeoError> {
+		let addr = ip.trim().parse::<IpAddr>().map_err(|_| GeoError::InvalidAddress(ip.into()))?;
+		self.locate(addr)
+	}
+}
+
+/// Map an address onto a single 128-bit key space. IPv4 addresses are mapped
+/// to `::ffff:a.b.c.d` so both families can live in one table.
+fn address_key(ip: IpAddr) -> u128 {
+	match ip {
+		IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+		IpAddr::V6(ip) => u128::from(ip),
+	}
+}
+
+/// Deterministic stand-in for a real database: every address is mapped to a
+/// pseudo-random point derived from the blake2 hash of all of its bytes, which
+/// stays the same across platforms and dependency upgrades. Only meant for tests
+/// and development chains, the coordinates have nothing to do with reality.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StubLocator;
+
+impl GeoLocator for StubLocator {
+	fn locate(&self, ip: IpAddr) -> Result<GeoPoint, GeoError> {
+		let hash = blake2_256(&address_key(ip).to_be_bytes());
+		let word = |at: usize| {
+			let mut bytes = [0u8; 8];
+			bytes.copy_from_slice(&hash[at..at + 8]);
+			u64::from_le_bytes(bytes)
+		};
+		// Latitudes include both poles, longitudes only the western antimeridian.
+		let lat = (word(0) % 180_000_001) as i32 - 90_000_000;
+		let lon = (word(8) % 360_000_000) as i32 - 180_000_000;
+		Ok(GeoPoint::new(lat, lon))
+	}
+}
+
+#[derive(Clone, Debug)]
+struct IpRange {
+	start: u128,
+	end: u128,
+	location: GeoPoint,
+}
+
+/// Offline GeoIP database loaded from a CSV range table.
+///
+/// Each non-empty line that does not start with `#` has the form
+/// `start,end,latitude,longitude`, where `start` and `end` are inclusive
+/// addresses of the same family. Ranges must not overlap.
+#[derive(Clone, Debug)]
+pub struct CsvRangeLocator {
+	ranges: Vec<IpRange>,
+}
+
+impl CsvRangeLocator {
+	/// Load the table from a file.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GeoError> {
+		let file = File::open(path.as_ref()).map_err(|err| {
+			GeoError::Database(format!("cannot open {}: {}", path.as_ref().display(), err))
+		})?;
+		Self::from_reader(BufReader::new(file))
+	}
+
+	/// Load the table from any buffered reader.
+	pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, GeoError> {
+		let mut ranges = Vec::new();
+		for (index, line) in reader.lines().enumerate() {
+			let line = line.map_err(|err| GeoError::Database(err.to_string()))?;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let range = parse_range(line)
+				.map_err(|err| GeoError::Database(format!("line {}: {}", index + 1, err)))?;
+			ranges.push(range);
+		}
+
+		ranges.sort_by_key(|range| range.start);
+		if let Some(pair) = ranges.windows(2).find(|pair| pair[0].end >= pair[1].start) {
+			return Err(GeoError::Database(format!(
+				"overlapping ranges starting at {:#x} and {:#x}",
+				pair[0].start, pair[1].start,
+			)));
+		}
+
+		Ok(Self { ranges })
+	}
+
+	/// Number of ranges in the table.
+	pub fn len(&self) -> usize {
+		self.ranges.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+}
+
+fn parse_range(line: &str) -> Result<IpRange, String> {
+	let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+	if fields.len() != 4 {
+		return Err(format!("expected 4 fields, found {}", fields.len()));
+	}
+
+	let start: IpAddr = fields[0].parse().map_err(|_| format!("invalid address `{}`", fields[0]))?;
+	let end: IpAddr = fields[1].parse().map_err(|_| format!("invalid address `{}`", fields[1]))?;
+	if start.is_ipv4() != end.is_ipv4() {
+		return Err("range mixes IPv4 and IPv6".into());
+	}
+	let (start, end) = (address_key(start), address_key(end));
+	if start > end {
+		return Err("range start is above its end".into());
+	}
+
+	let lat: f64 = fields[2].parse().map_err(|_| format!("invalid latitude `{}`", fields[2]))?;
+	let lon: f64 = fields[3].parse().map_err(|_| format!("invalid longitude `{}`", fields[3]))?;
+	// `from_degrees` saturates NaN to zero, which would pass the range check below.
+	if !lat.is_finite() || !lon.is_finite() {
+		return Err(format!("coordinates ({}, {}) are not finite", lat, lon));
+	}
+	let location = GeoPoint::from_degrees(lat, lon);
+	if !location.is_valid() {
+		return Err(format!("coordinates ({}, {}) out of range", lat, lon));
+	}
+
+	Ok(IpRange { start, end, location })
+}
+
+impl GeoLocator for CsvRangeLocator {
+	fn locate(&self, ip: IpAddr) -> Result<GeoPoint, GeoError> {
+		let key = address_key(ip);
+		// Ranges are sorted and disjoint, so the only candidate is the last one
+		// starting at or before the address.
+		let candidate = match self.ranges.partition_point(|range| range.start <= key) {
+			0 => None,
+			index => Some(&self.ranges[index - 1]),
+		};
+
+		match candidate {
+			Some(range) if key <= range.end => Ok(range.location),
+			_ => Err(GeoError::NotFound(ip)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TABLE: &str = "\
+# start,end,latitude,longitude
+1.0.0.0,1.0.0.255,-33.49,143.21
+4.3.2.0,4.3.2.255,37.75,-97.82
+
+2001:db8::,2001:db8::ffff,52.52,13.40
+";
+
+	fn locator() -> CsvRangeLocator {
+		CsvRangeLocator::from_reader(TABLE.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn locates_ipv4_and_ipv6() {
+		let locator = locator();
+		assert_eq!(locator.len(), 3);
+		assert_eq!(locator.locate_str("1.0.0.7"), Ok(GeoPoint::new(-33_490_000, 143_210_000)));
+		assert_eq!(locator.locate_str("4.3.2.1"), Ok(GeoPoint::new(37_750_000, -97_820_000)));
+		assert_eq!(locator.locate_str("2001:db8::42"), Ok(GeoPoint::new(52_520_000, 13_400_000)));
+	}
+
+	#[test]
+	fn reports_unknown_and_invalid_addresses() {
+		let locator = locator();
+		assert_eq!(locator.locate_str("1.2.3.4"), Err(GeoError::NotFound("1.2.3.4".parse().unwrap())));
+		assert_eq!(locator.locate_str("0.0.0.0"), Err(GeoError::NotFound("0.0.0.0".parse().unwrap())));
+		assert_eq!(locator.locate_str("1.2.3"), Err(GeoError::InvalidAddress("1.2.3".into())));
+	}
+
+	#[test]
+	fn rejects_malformed_tables() {
+		for table in [
+			"1.0.0.0,1.0.0.255,-33.49\n",
+			"1.0.0.9,1.0.0.0,0,0\n",
+			"1.0.0.0,2001:db8::,0,0\n",
+			"1.0.0.0,1.0.0.255,91,0\n",
+			"1.0.0.0,1.0.0.255,NaN,0\n",
+			"1.0.0.0,1.0.0.255,0,NaN\n",
+			"1.0.0.0,1.0.0.255,0,inf\n",
+			"1.0.0.0,1.0.0.255,0,0\n1.0.0.128,1.0.1.0,0,0\n",
+		] {
+			assert!(matches!(
+				CsvRangeLocator::from_reader(table.as_bytes()),
+				Err(GeoError::Database(_))
+			));
+		}
+	}
+
+	#[test]
+	fn stub_is_deterministic_and_uses_every_byte() {
+		let a = StubLocator.locate_str("1.2.3.4").unwrap();
+		assert_eq!(StubLocator.locate_str("1.2.3.4").unwrap(), a);
+		assert_ne!(StubLocator.locate_str("4.3.2.1").unwrap(), a);
+	}
+
+	#[test]
+	fn stub_locations_are_pinned() {
+		// Nodes of a development network must agree on these, whatever they were built with.
+		assert_eq!(StubLocator.locate_str("1.2.3.4"), Ok(GeoPoint::new(-32_481_462, 116_360_056)));
+		assert_eq!(
+			StubLocator.locate_str("::ffff:1.2.3.4"),
+			StubLocator.locate_str("1.2.3.4")
+		);
+	}
+}
@@ -0,0 +1,238 @@
+//! Geo mining simulator.
+//!
+//! Replays a number of blocks mined by simulated miners, each with an IP address or
+//! coordinates and a hashrate. For every block only the miners the node would place inside
+//! the mining zone grind nonces, and the winning seal is checked with the chosen
+//! `PowAlgorithm`. The report shows the share of blocks per miner and region against their
+//! share of the hashrate, how much of the globe the zone covers over time and how often
+//! competing seals would have forked the chain, so that the zone parameters can be tuned
+//! before they are changed on a live network.
+
+mod miners;
+mod sim;
+
+use clap::Parser;
+use geopow_primitives::geohash;
+use miners::{Distribution, Miner, SimLocator};
+use rand::{rngs::StdRng, SeedableRng};
+use sha3pow::{CsvRangeLocator, GeoLocator, MiningZoneParams, StubLocator, ZoneMode};
+use sim::{Algorithm, Config, Pow, Report};
+use sp_core::{hashing::blake2_256, U256};
+use std::{collections::BTreeMap, error::Error, fs, path::PathBuf};
+
+#[derive(Debug, Parser)]
+struct Cli {
+	/// File of simulated miners. Each non-empty line that does not start with `#` has the
+	/// form `name,hashrate,ip` or `name,hashrate,latitude,longitude`, with the hashrate in
+	/// nonces per round.
+	#[clap(long, value_name = "PATH")]
+	miners: Option<PathBuf>,
+
+	/// Number of additional miners at random IPv4 addresses.
+	#[clap(long, value_name = "COUNT", default_value = "0")]
+	random_miners: usize,
+
+	/// How the hashrates of random miners are distributed, `equal` or `pareto`.
+	#[clap(long, value_name = "DISTRIBUTION", default_value = "equal")]
+	distribution: Distribution,
+
+	/// Mean hashrate of random miners, in nonces per round.
+	#[clap(long, value_name = "NONCES", default_value = "100")]
+	mean_hashrate: u64,
+
+	/// Offline GeoIP database locating miners given by IP address, in the format of the
+	/// node's `--geoip-db`. Without one addresses are placed by the deterministic stub
+	/// locator.
+	#[clap(long, value_name = "PATH")]
+	geoip_db: Option<PathBuf>,
+
+	/// Number of blocks to replay.
+	#[clap(long, value_name = "COUNT", default_value = "1000")]
+	blocks: u64,
+
+	/// Seal algorithm, `minipow` or `sha3`.
+	#[clap(long, value_name = "ALGORITHM", default_value = "sha3")]
+	algorithm: Algorithm,
+
+	/// Fixed difficulty, the expected number of nonces per block.
+	#[clap(long, value_name = "DIFFICULTY", default_value = "1000")]
+	difficulty: u64,
+
+	/// Raster cells per degree of latitude and longitude.
+	#[clap(long, value_name = "CELLS", default_value = "1")]
+	resolution: u32,
+
+	/// Distance in noise space between neighbouring cells, in millionths.
+	#[clap(long, value_name = "MILLIONTHS", default_value = "30000")]
+	scale: u32,
+
+	/// Noise threshold above which cells are inside the zone, in millionths.
+	#[clap(long, value_name = "MILLIONTHS", default_value = "200000", allow_hyphen_values = true)]
+	threshold: i32,
+
+	/// Number of noise octaves.
+	#[clap(long, value_name = "COUNT", default_value = "1")]
+	octaves: u8,
+
+	/// Let the zone drift, advancing by one noise unit every this many blocks. Without it
+	/// every block is reseeded with its parent's hash.
+	#[clap(long, value_name = "BLOCKS")]
+	drift_period: Option<u32>,
+
+	/// Rounds it takes a block to reach every other miner. Seals found within this many
+	/// rounds after the winning one count as orphans.
+	#[clap(long, value_name = "ROUNDS", default_value = "1")]
+	propagation_rounds: u64,
+
+	/// Sample the zone coverage every this many blocks, never if zero.
+	#[clap(long, value_name = "BLOCKS", default_value = "100")]
+	coverage_every: u64,
+
+	/// Number of geohash characters of the regions blocks are tallied by.
+	#[clap(long, value_name = "CHARS", default_value = "2")]
+	region_precision: u8,
+
+	/// Seed of the random miners, the genesis hash and all other randomness, so that runs
+	/// can be repeated.
+	#[clap(long, value_name = "SEED", default_value = "0")]
+	seed: u64,
+}
+
+impl Cli {
+	fn params(&self) -> MiningZoneParams {
+		MiningZoneParams {
+			resolution: self.resolution,
+			scale: self.scale,
+			threshold: self.threshold,
+			octaves: self.octaves,
+			mode: match self.drift_period {
+				Some(period) => ZoneMode::Drifting { period },
+				None => ZoneMode::Reseeded,
+			},
+		}
+	}
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+	let cli = Cli::parse();
+	let params = cli.params();
+	if !params.is_valid() {
+		return Err(format!("Invalid mining zone parameters {:?}", params).into())
+	}
+
+	let fallback: Box<dyn GeoLocator> = match &cli.geoip_db {
+		Some(path) => Box::new(CsvRangeLocator::open(path)?),
+		None => Box::new(StubLocator),
+	};
+	let mut locator = SimLocator::new(fallback);
+	let mut rng = StdRng::seed_from_u64(cli.seed);
+	let mut miners = match &cli.miners {
+		Some(path) => miners::parse_miners(&fs::read_to_string(path)?, &mut locator)?,
+		None => Vec::new(),
+	};
+	miners.extend(miners::random_miners(
+		cli.random_miners,
+		cli.distribution,
+		cli.mean_hashrate,
+		&mut rng,
+	));
+	if miners.is_empty() {
+		return Err("No miners, pass --miners or --random-miners".into())
+	}
+
+	let config = Config {
+		blocks: cli.blocks,
+		params,
+		propagation_rounds: cli.propagation_rounds,
+		coverage_every: cli.coverage_every,
+	};
+	let pow = Pow::new(cli.algorithm, U256::from(cli.difficulty));
+	let genesis = blake2_256(&cli.seed.to_le_bytes()).into();
+	let report = sim::simulate(&miners, &locator, &pow, &config, genesis, &mut rng)
+		.map_err(|err| err.to_string())?;
+
+	print_report(&cli, &miners, &locator, &report);
+	Ok(())
+}
+
+/// `part` as a percentage of `total`.
+fn percent(part: u64, total: u64) -> f64 {
+	if total == 0 {
+		return 0.0
+	}
+	100.0 * part as f64 / total as f64
+}
+
+fn print_report(cli: &Cli, miners: &[Miner], locator: &SimLocator, report: &Report) {
+	println!("Zone: {:?}", cli.params());
+	println!("Algorithm: {:?} at difficulty {}", cli.algorithm, cli.difficulty);
+	println!("Blocks mined: {} of {}", report.blocks, cli.blocks);
+	if let Some(number) = report.stalled {
+		println!("Stalled at block {}: no miner was inside the zone", number);
+	}
+	if report.blocks > 0 {
+		println!("Mean block time: {:.2} rounds", report.rounds as f64 / report.blocks as f64);
+	}
+	let orphaned: u64 = report.miners.iter().map(|stats| stats.orphaned).sum();
+	println!(
+		"Forks: {} blocks ({:.2}%) with {} orphaned seals",
+		report.forks,
+		percent(report.forks, report.blocks),
+		orphaned
+	);
+
+	if !report.coverage.is_empty() {
+		let coverages = report.coverage.iter().map(|(_, coverage)| *coverage);
+		let min = coverages.clone().fold(f64::INFINITY, f64::min);
+		let max = coverages.clone().fold(0.0, f64::max);
+		let mean = coverages.sum::<f64>() / report.coverage.len() as f64;
+		println!();
+		println!(
+			"Zone coverage: min {:.2}%, mean {:.2}%, max {:.2}%",
+			100.0 * min,
+			100.0 * mean,
+			100.0 * max
+		);
+		for (number, coverage) in &report.coverage {
+			println!("  block {:>8}: {:>6.2}%", number, 100.0 * coverage);
+		}
+	}
+
+	let total_hashrate: u64 = miners.iter().map(|miner| miner.hashrate).sum();
+	println!();
+	println!(
+		"{:<16} {:<40} {:>8} {:>8} {:>8} {:>8} {:>8}",
+		"Miner", "IP", "Region", "Hash %", "Block %", "In zone", "Orphans"
+	);
+	let mut regions = BTreeMap::<String, (usize, u64, u64)>::new();
+	for (miner, stats) in miners.iter().zip(&report.miners) {
+		let region = match locator.locate(miner.ip) {
+			Ok(location) => geohash::encode(location.lat, location.lon, cli.region_precision),
+			Err(_) => "?".into(),
+		};
+		println!(
+			"{:<16} {:<40} {:>8} {:>7.2}% {:>7.2}% {:>7.2}% {:>8}",
+			miner.name,
+			miner.ip,
+			region,
+			percent(miner.hashrate, total_hashrate),
+			percent(stats.mined, report.blocks),
+			percent(stats.eligible, report.blocks + report.stalled.is_some() as u64),
+			stats.orphaned
+		);
+		let entry = regions.entry(region).or_default();
+		*entry = (entry.0 + 1, entry.1 + miner.hashrate, entry.2 + stats.mined);
+	}
+
+	println!();
+	println!("{:<8} {:>8} {:>8} {:>8}", "Region", "Miners", "Hash %", "Block %");
+	for (region, (count, hashrate, mined)) in regions {
+		println!(
+			"{:<8} {:>8} {:>7.2}% {:>7.2}%",
+			region,
+			count,
+			percent(hashrate, total_hashrate),
+			percent(mined, report.blocks)
+		);
+	}
+}
@@ -0,0 +1,253 @@
+//! The simulated miners: where they are and how fast they hash.
+
+use rand::{rngs::StdRng, Rng};
+use sha3pow::{GeoError, GeoLocator, GeoPoint};
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv4Addr},
+	str::FromStr,
+};
+
+/// Addresses of miners given by coordinates are taken from the 198.18.0.0/15 benchmarking
+/// range, so they never collide with the addresses of other miners.
+const PLACED_RANGE: (u32, u32) = (0xc612_0000, 0xc613_ffff);
+
+/// A simulated miner.
+#[derive(Clone, Debug)]
+pub struct Miner {
+	/// Name the miner is reported under.
+	pub name: String,
+	/// Address the miner claims in its blocks.
+	pub ip: IpAddr,
+	/// Number of nonces the miner tries per round.
+	pub hashrate: u64,
+}
+
+/// How the hashrates of random miners are distributed.
+#[derive(Clone, Copy, Debug)]
+pub enum Distribution {
+	/// Every miner gets the mean hashrate.
+	Equal,
+	/// Hashrates follow a Pareto distribution with the mean hashrate, where about a fifth of
+	/// the miners holds four fifths of the total.
+	Pareto,
+}
+
+impl FromStr for Distribution {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"equal" => Ok(Self::Equal),
+			"pareto" => Ok(Self::Pareto),
+			other => Err(format!("Unsupported hashrate distribution `{}`", other)),
+		}
+	}
+}
+
+impl Distribution {
+	/// Shape of the Pareto distribution giving the 80-20 rule.
+	const PARETO_ALPHA: f64 = 1.16;
+
+	/// Draw a hashrate of at least one nonce per round.
+	fn sample(self, mean: u64, rng: &mut StdRng) -> u64 {
+		let hashrate = match self {
+			Self::Equal => mean as f64,
+			Self::Pareto => {
+				let alpha = Self::PARETO_ALPHA;
+				let minimum = mean as f64 * (alpha - 1.0) / alpha;
+				// `gen` samples `[0, 1)`, the inverse CDF needs `(0, 1]`.
+				minimum / (1.0 - rng.gen::<f64>()).powf(1.0 / alpha)
+			},
+		};
+		(hashrate.round() as u64).max(1)
+	}
+}
+
+/// Locates the simulated miners. Miners given by coordinates are looked up in a table, all
+/// other addresses are passed on to the locator the node would use.
+pub struct SimLocator {
+	placed: HashMap<IpAddr, GeoPoint>,
+	fallback: Box<dyn GeoLocator>,
+}
+
+impl SimLocator {
+	pub fn new(fallback: Box<dyn GeoLocator>) -> Self {
+		Self { placed: HashMap::new(), fallback }
+	}
+
+	/// Register a miner at `location` and return the address it claims.
+	fn place(&mut self, location: GeoPoint) -> Result<IpAddr, String> {
+		let offset = self.placed.len() as u32;
+		if offset > PLACED_RANGE.1 - PLACED_RANGE.0 {
+			return Err("Too many miners given by coordinates".into())
+		}
+		let ip = IpAddr::V4(Ipv4Addr::from(PLACED_RANGE.0 + offset));
+		self.placed.insert(ip, location);
+		Ok(ip)
+	}
+}
+
+impl GeoLocator for SimLocator {
+	fn locate(&self, ip: IpAddr) -> Result<GeoPoint, GeoError> {
+		match self.placed.get(&ip) {
+			Some(location) => Ok(*location),
+			None => self.fallback.locate(ip),
+		}
+	}
+}
+
+/// Whether `ip` lies in the range reserved for miners given by coordinates.
+fn is_placed(ip: &IpAddr) -> bool {
+	match ip {
+		IpAddr::V4(ip) => (PLACED_RANGE.0..=PLACED_RANGE.1).contains(&u32::from(*ip)),
+		IpAddr::V6(_) => false,
+	}
+}
+
+/// Read miners from a list. Each non-empty line that does not start with `#` has the form
+/// `name,hashrate,ip` or `name,hashrate,latitude,longitude`, with the hashrate in nonces per
+/// round and the coordinates in degrees. Miners given by coordinates are registered with
+/// `locator`.
+pub fn parse_miners(text: &str, locator: &mut SimLocator) -> Result<Vec<Miner>, String> {
+	let mut miners = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let malformed = |what: &str| format!("Line {} of the miners: {}", index + 1, what);
+
+		let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+		let (name, hashrate) = match fields[..] {
+			[name, hashrate, ..] if fields.len() <= 4 => (name, hashrate),
+			_ => return Err(malformed("expected `name,hashrate,ip` or `name,hashrate,lat,lon`")),
+		};
+		let hashrate = hashrate
+			.parse::<u64>()
+			.ok()
+			.filter(|hashrate| *hashrate > 0)
+			.ok_or_else(|| malformed("invalid hashrate"))?;
+		let ip = match fields[2..] {
+			[ip] => {
+				let ip = ip.parse::<IpAddr>().map_err(|_| malformed("invalid IP address"))?;
+				if is_placed(&ip) {
+					return Err(malformed("198.18.0.0/15 is reserved for miners at coordinates"))
+				}
+				ip
+			},
+			[lat, lon] => {
+				// `GeoPoint` would turn NaN into 0, placing the miner on the equator.
+				let degrees = |value: &str| {
+					value
+						.parse::<f64>()
+						.ok()
+						.filter(|degrees| degrees.is_finite())
+						.ok_or_else(|| malformed("invalid coordinate"))
+				};
+				let location = GeoPoint::from_degrees(degrees(lat)?, degrees(lon)?);
+				if !location.is_valid() {
+					return Err(malformed("coordinates are not on the globe"))
+				}
+				locator.place(location)?
+			},
+			_ => return Err(malformed("expected an IP address or coordinates")),
+		};
+		miners.push(Miner { name: name.to_string(), ip, hashrate });
+	}
+	Ok(miners)
+}
+
+/// `count` miners at random IPv4 addresses, with hashrates drawn from `distribution`.
+pub fn random_miners(
+	count: usize,
+	distribution: Distribution,
+	mean_hashrate: u64,
+	rng: &mut StdRng,
+) -> Vec<Miner> {
+	(0..count)
+		.map(|index| {
+			let ip = loop {
+				let ip = IpAddr::V4(Ipv4Addr::from(rng.gen::<u32>()));
+				if !is_placed(&ip) {
+					break ip;
+				}
+			};
+			let hashrate = distribution.sample(mean_hashrate, rng);
+			Miner { name: format!("random-{}", index), ip, hashrate }
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sha3pow::StubLocator;
+
+	fn parse(text: &str) -> Result<(Vec<Miner>, SimLocator), String> {
+		let mut locator = SimLocator::new(Box::new(StubLocator));
+		let miners = parse_miners(text, &mut locator)?;
+		Ok((miners, locator))
+	}
+
+	#[test]
+	fn parses_miners_by_ip_and_by_coordinates() {
+		let text = "# name,hashrate,location\n\n alice, 10, 203.0.113.7 \n\
+			bob,5,52.52,13.40\ncarol,1,2001:db8::1\n";
+		let (miners, locator) = parse(text).unwrap();
+
+		let summary: Vec<(&str, u64, IpAddr)> = miners
+			.iter()
+			.map(|miner| (miner.name.as_str(), miner.hashrate, miner.ip))
+			.collect();
+		let bob = IpAddr::V4(Ipv4Addr::from(PLACED_RANGE.0));
+		assert_eq!(
+			summary,
+			vec![
+				("alice", 10, "203.0.113.7".parse().unwrap()),
+				("bob", 5, bob),
+				("carol", 1, "2001:db8::1".parse().unwrap()),
+			],
+		);
+		assert_eq!(locator.locate(bob), Ok(GeoPoint::from_degrees(52.52, 13.40)));
+		// Addresses given explicitly go to the fallback locator.
+		assert_eq!(locator.locate(miners[0].ip), StubLocator.locate(miners[0].ip));
+	}
+
+	#[test]
+	fn miners_at_coordinates_get_distinct_addresses() {
+		let (miners, locator) = parse("a,1,10,20\nb,1,10,20\nc,1,-10,-20").unwrap();
+		let ips: Vec<_> = miners.iter().map(|miner| miner.ip).collect();
+		assert_eq!(ips.len(), 3);
+		assert!(ips.iter().all(is_placed));
+		assert!(ips[0] != ips[1] && ips[1] != ips[2] && ips[0] != ips[2]);
+		assert_eq!(locator.locate(ips[2]), Ok(GeoPoint::from_degrees(-10.0, -20.0)));
+	}
+
+	#[test]
+	fn rejects_malformed_lines() {
+		for (line, error) in [
+			("alice", "expected `name,hashrate,ip` or `name,hashrate,lat,lon`"),
+			("alice,1,2,3,4", "expected `name,hashrate,ip` or `name,hashrate,lat,lon`"),
+			("alice,10", "expected an IP address or coordinates"),
+			("alice,0,203.0.113.7", "invalid hashrate"),
+			("alice,-1,203.0.113.7", "invalid hashrate"),
+			("alice,fast,203.0.113.7", "invalid hashrate"),
+			("alice,10,203.0.113", "invalid IP address"),
+			("alice,10,198.18.0.1", "198.18.0.0/15 is reserved for miners at coordinates"),
+			("alice,10,north,east", "invalid coordinate"),
+			("alice,10,NaN,0", "invalid coordinate"),
+			("alice,10,0,inf", "invalid coordinate"),
+			("alice,10,91,0", "coordinates are not on the globe"),
+			("alice,10,0,-180.5", "coordinates are not on the globe"),
+		] {
+			let text = format!("# miners\n\nbob,1,203.0.113.8\n{}\n", line);
+			assert_eq!(
+				parse(&text).map(|_| ()),
+				Err(format!("Line 4 of the miners: {}", error)),
+				"{}",
+				line
+			);
+		}
+	}
+}
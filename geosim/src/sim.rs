@@ -0,0 +1,313 @@
+//! Replaying blocks: which miners the zone lets in and who seals first.
+//!
+//! Time advances in rounds, in which every miner inside the zone tries as many nonces as its
+//! hashrate. The miner sealing the block in the earliest round wins it, ties are broken at
+//! random as by the network. Competing seals found before the winning block has propagated
+//! would have forked the chain and are counted as orphans.
+
+use crate::miners::Miner;
+use minipow::MiniPow;
+use parity_scale_codec::Encode;
+use rand::{rngs::StdRng, Rng};
+use sc_consensus_pow::{Error, PowAlgorithm};
+use sha3pow::{check_seal, geo, hash_meets_difficulty, Compute, GeoLocator, MiningZoneParams};
+use sp_consensus_pow::Seal as RawSeal;
+use sp_core::{hashing::blake2_256, H256, U256};
+use sp_runtime::{
+	generic::{self, BlockId},
+	traits::BlakeTwo256,
+	OpaqueExtrinsic,
+};
+use std::str::FromStr;
+
+/// The block type seals are verified for. Only its hash matters to the algorithms.
+pub type Block = generic::Block<generic::Header<u64, BlakeTwo256>, OpaqueExtrinsic>;
+
+/// The seal algorithms blocks can be mined with.
+#[derive(Clone, Copy, Debug)]
+pub enum Algorithm {
+	MiniPow,
+	Sha3,
+}
+
+impl FromStr for Algorithm {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"minipow" => Ok(Self::MiniPow),
+			"sha3" => Ok(Self::Sha3),
+			other => Err(format!("Unsupported PoW algorithm `{}`", other)),
+		}
+	}
+}
+
+/// The chosen algorithm at a fixed difficulty. The zone is checked before miners start
+/// grinding, so sha3 seals are verified without the zone check of the node's algorithm.
+#[derive(Clone)]
+pub enum Pow {
+	MiniPow(MiniPow),
+	Sha3(U256),
+}
+
+impl Pow {
+	pub fn new(algorithm: Algorithm, difficulty: U256) -> Self {
+		match algorithm {
+			Algorithm::MiniPow => Self::MiniPow(MiniPow::new(difficulty)),
+			Algorithm::Sha3 => Self::Sha3(difficulty.max(U256::one())),
+		}
+	}
+
+	/// Seal `pre_hash` with `nonce` if the resulting work meets `difficulty`.
+	fn seal(&self, pre_hash: &H256, nonce: U256, difficulty: U256) -> Option<RawSeal> {
+		match self {
+			Self::MiniPow(pow) => pow.seal(pre_hash.as_bytes(), nonce, difficulty),
+			Self::Sha3(_) => {
+				let seal = Compute { difficulty, pre_hash: *pre_hash, nonce }.compute();
+				hash_meets_difficulty(&seal.work, difficulty).then(|| seal.encode())
+			},
+		}
+	}
+}
+
+impl PowAlgorithm<Block> for Pow {
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: H256) -> Result<Self::Difficulty, Error<Block>> {
+		match self {
+			Self::MiniPow(pow) => PowAlgorithm::<Block>::difficulty(pow, parent),
+			Self::Sha3(difficulty) => Ok(*difficulty),
+		}
+	}
+
+	fn verify(
+		&self,
+		parent: &BlockId<Block>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &RawSeal,
+		difficulty: Self::Difficulty,
+	) -> Result<bool, Error<Block>> {
+		match self {
+			Self::MiniPow(pow) =>
+				PowAlgorithm::<Block>::verify(pow, parent, pre_hash, pre_digest, seal, difficulty),
+			Self::Sha3(_) => Ok(check_seal(pre_hash, seal, difficulty).is_ok()),
+		}
+	}
+}
+
+/// What to simulate.
+#[derive(Clone, Debug)]
+pub struct Config {
+	/// Number of blocks to mine.
+	pub blocks: u64,
+	/// Parameters of the mining zone.
+	pub params: MiningZoneParams,
+	/// Rounds it takes a block to reach every other miner.
+	pub propagation_rounds: u64,
+	/// Sample the zone coverage every this many blocks, never if zero.
+	pub coverage_every: u64,
+}
+
+/// What happened to one miner.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct MinerStats {
+	/// Blocks the miner was inside the zone for.
+	pub eligible: u64,
+	/// Blocks the miner sealed first.
+	pub mined: u64,
+	/// Competing seals the miner found before the winning block reached it.
+	pub orphaned: u64,
+}
+
+/// Outcome of a simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+	/// Statistics in the order of the miners.
+	pub miners: Vec<MinerStats>,
+	/// Number of blocks mined.
+	pub blocks: u64,
+	/// Rounds it took to mine them.
+	pub rounds: u64,
+	/// Blocks that had at least one competing seal.
+	pub forks: u64,
+	/// Fraction of the earth's surface inside the zone, by block number.
+	pub coverage: Vec<(u64, f64)>,
+	/// The block no miner was inside the zone for, which stopped the chain.
+	pub stalled: Option<u64>,
+}
+
+/// A miner grinding on one block.
+struct Attempt {
+	miner: usize,
+	pre_hash: H256,
+	nonce: U256,
+	found: Option<(u64, RawSeal)>,
+}
+
+/// Mine `config.blocks` blocks on top of `genesis`.
+pub fn simulate(
+	miners: &[Miner],
+	locator: &dyn GeoLocator,
+	pow: &Pow,
+	config: &Config,
+	genesis: H256,
+	rng: &mut StdRng,
+) -> Result<Report, Error<Block>> {
+	let mut report =
+		Report { miners: vec![MinerStats::default(); miners.len()], ..Default::default() };
+	let mut parent = genesis;
+
+	for number in 1..=config.blocks {
+		let zone = geo::ZoneBlock { genesis, parent, number };
+		if config.coverage_every > 0 && (number - 1) % config.coverage_every == 0 {
+			let raster = geo::raster(&zone, &config.params);
+			report.coverage.push((number, geo::coverage(&config.params, &raster)));
+		}
+
+		let eligible: Vec<usize> = (0..miners.len())
+			.filter(|index| {
+				let ip = miners[*index].ip.to_string();
+				geo::node_is_on_mining_zone(&zone, locator, &ip, &config.params)
+			})
+			.collect();
+		if eligible.is_empty() {
+			report.stalled = Some(number);
+			break;
+		}
+
+		let difficulty = pow.difficulty(parent)?;
+		let mut attempts: Vec<Attempt> = eligible
+			.into_iter()
+			.map(|miner| {
+				report.miners[miner].eligible += 1;
+				// Every miner seals a block of its own, crediting itself.
+				let pre_hash = blake2_256(&(parent, number, miner as u64).encode()).into();
+				Attempt { miner, pre_hash, nonce: U256::from(rng.gen::<u64>()), found: None }
+			})
+			.collect();
+
+		let mut first = None;
+		let mut round = 0;
+		while first.map_or(true, |first| round <= first + config.propagation_rounds) {
+			for attempt in attempts.iter_mut().filter(|attempt| attempt.found.is_none()) {
+				for _ in 0..miners[attempt.miner].hashrate {
+					if let Some(seal) = pow.seal(&attempt.pre_hash, attempt.nonce, difficulty) {
+						attempt.found = Some((round, seal));
+						first = first.or(Some(round));
+						break;
+					}
+					attempt.nonce = attempt.nonce.overflowing_add(U256::one()).0;
+				}
+			}
+			round += 1;
+		}
+		let first = first.expect("Rounds only stop once a seal was found; qed");
+
+		let mut found: Vec<(u64, usize, H256, RawSeal)> = attempts
+			.into_iter()
+			.filter_map(|attempt| {
+				let (round, seal) = attempt.found?;
+				Some((round, attempt.miner, attempt.pre_hash, seal))
+			})
+			.collect();
+		let tied = found.iter().filter(|(round, ..)| *round == first).count();
+		let winner = found
+			.iter()
+			.enumerate()
+			.filter(|(_, (round, ..))| *round == first)
+			.nth(rng.gen_range(0..tied))
+			.map(|(index, _)| index)
+			.expect("At least one seal was found in the first round; qed");
+		let (_, winner, pre_hash, seal) = found.swap_remove(winner);
+
+		if !pow.verify(&BlockId::Hash(parent), &pre_hash, None, &seal, difficulty)? {
+			return Err(Error::Environment(format!(
+				"Seal of block {} by {} does not verify",
+				number, miners[winner].name
+			)))
+		}
+
+		report.miners[winner].mined += 1;
+		for (_, miner, ..) in &found {
+			report.miners[*miner].orphaned += 1;
+		}
+		report.forks += !found.is_empty() as u64;
+		report.blocks += 1;
+		report.rounds += first + 1;
+		parent = blake2_256(&(parent, pre_hash, seal).encode()).into();
+	}
+
+	Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::miners::{parse_miners, SimLocator};
+	use rand::SeedableRng;
+	use sha3pow::StubLocator;
+
+	/// Three miners far apart, given by coordinates.
+	fn miners() -> (Vec<Miner>, SimLocator) {
+		let mut locator = SimLocator::new(Box::new(StubLocator));
+		let text = "paris,3,48.86,2.35\nberlin,2,52.52,13.40\nsydney,1,-33.87,151.21";
+		let miners = parse_miners(text, &mut locator).unwrap();
+		(miners, locator)
+	}
+
+	/// Mine `blocks` blocks in a zone covering the whole globe.
+	fn config(blocks: u64, propagation_rounds: u64) -> Config {
+		let params = MiningZoneParams { threshold: -1_000_000, ..Default::default() };
+		Config { blocks, params, propagation_rounds, coverage_every: 0 }
+	}
+
+	fn run(config: &Config, seed: u64) -> Report {
+		let (miners, locator) = miners();
+		let pow = Pow::new(Algorithm::Sha3, U256::from(16));
+		let mut rng = StdRng::seed_from_u64(seed);
+		simulate(&miners, &locator, &pow, config, H256::repeat_byte(1), &mut rng).unwrap()
+	}
+
+	#[test]
+	fn same_seed_gives_the_same_report() {
+		let config = Config { coverage_every: 10, ..config(50, 1) };
+		let report = run(&config, 7);
+		assert_eq!(report.blocks, 50);
+		assert_eq!(report.coverage.len(), 5);
+		assert_eq!(run(&config, 7), report);
+		assert_ne!(run(&config, 8), report);
+	}
+
+	#[test]
+	fn stalls_when_no_miner_is_in_the_zone() {
+		let mut config = config(10, 1);
+		config.params.threshold = 1_000_000;
+		let report = run(&config, 7);
+		assert_eq!(report.stalled, Some(1));
+		assert_eq!((report.blocks, report.rounds, report.forks), (0, 0, 0));
+		assert!(report.miners.iter().all(|stats| *stats == MinerStats::default()));
+	}
+
+	#[test]
+	fn counts_seals_found_before_propagation_as_orphans() {
+		let orphaned =
+			|report: &Report| report.miners.iter().map(|stats| stats.orphaned).sum::<u64>();
+		let mined = |report: &Report| report.miners.iter().map(|stats| stats.mined).sum::<u64>();
+
+		// Instant propagation only orphans seals found in the same round as the winner.
+		let instant = run(&config(20, 0), 7);
+		assert_eq!(instant.stalled, None);
+		assert_eq!(mined(&instant), 20);
+		assert!(instant.miners.iter().all(|stats| stats.eligible == 20));
+		assert!(instant.forks <= orphaned(&instant));
+
+		// Given long enough, every miner finds a seal for every block, and all but the
+		// winning one are orphaned.
+		let slow = run(&config(20, 1_000), 7);
+		assert_eq!(mined(&slow), 20);
+		assert_eq!(slow.forks, 20);
+		assert_eq!(orphaned(&slow), 2 * 20);
+		assert!(orphaned(&instant) < orphaned(&slow));
+	}
+}
@@ -0,0 +1,261 @@
+//! Reference external miner.
+//!
+//! Polls a mining node for the current block template with `mining_getWork`, grinds nonces
+//! on CPU threads and hands seals back with `mining_submitWork`. Every thread starts at a
+//! random nonce, so that any number of miners can work on the same template.
+
+use clap::Parser;
+use geopow_primitives::{hash_meets_difficulty, mini, Compute};
+use jsonrpsee::{core::client::ClientT, http_client::HttpClientBuilder, rpc_params};
+use parity_scale_codec::Encode;
+use serde::Deserialize;
+use sp_core::{Bytes, H256, U256};
+use std::{
+	error::Error,
+	str::FromStr,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, RwLock,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+/// Number of nonces tried between checks for new work.
+const BATCH_SIZE: u64 = 10_000;
+
+/// How long an idle thread waits before checking for work again.
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the hashrate is logged.
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Parser)]
+struct Cli {
+	/// HTTP RPC endpoint of a node started with `--mine`.
+	#[clap(long, value_name = "URL", default_value = "http://127.0.0.1:9933")]
+	url: String,
+
+	/// Number of CPU threads grinding nonces.
+	#[clap(long, value_name = "COUNT", default_value = "1")]
+	threads: usize,
+
+	/// How often the node is asked for new work, in milliseconds.
+	#[clap(long, value_name = "MILLIS", default_value = "1000")]
+	poll_interval: u64,
+}
+
+/// The seal algorithms the node may ask for.
+#[derive(Clone, Copy, Debug)]
+enum Algorithm {
+	MiniPow,
+	Sha3,
+}
+
+impl Algorithm {
+	/// Seal `pre_hash` with `nonce` if the resulting work meets `difficulty`.
+	fn seal(self, pre_hash: &H256, difficulty: U256, nonce: U256) -> Option<Vec<u8>> {
+		match self {
+			Self::MiniPow => mini::seal(pre_hash.as_bytes(), nonce, difficulty),
+			Self::Sha3 => {
+				let seal = Compute { difficulty, pre_hash: *pre_hash, nonce }.compute();
+				hash_meets_difficulty(&seal.work, difficulty).then(|| seal.encode())
+			},
+		}
+	}
+}
+
+impl FromStr for Algorithm {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"minipow" => Ok(Self::MiniPow),
+			"sha3" => Ok(Self::Sha3),
+			other => Err(format!("Unsupported PoW algorithm `{}`", other)),
+		}
+	}
+}
+
+/// Work as returned by `mining_getWork`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Work {
+	algorithm: String,
+	parent: H256,
+	pre_hash: H256,
+	difficulty: U256,
+}
+
+/// A template the threads are grinding on.
+#[derive(Clone, Copy, Debug)]
+struct Job {
+	algorithm: Algorithm,
+	pre_hash: H256,
+	difficulty: U256,
+}
+
+/// State shared between the RPC loop and the miner threads.
+#[derive(Default)]
+struct Shared {
+	job: RwLock<Option<Job>>,
+	/// Bumped whenever `job` changes.
+	version: AtomicU64,
+	hashes: AtomicU64,
+}
+
+impl Shared {
+	fn job(&self) -> Option<Job> {
+		*self.job.read().expect("Miner threads never panic while holding the lock; qed")
+	}
+
+	fn set_job(&self, job: Option<Job>) {
+		*self.job.write().expect("Miner threads never panic while holding the lock; qed") = job;
+		self.version.fetch_add(1, Ordering::Release);
+	}
+}
+
+/// Grind nonces on the current job until it changes, forever. Seals are sent to `found`.
+fn mine(shared: Arc<Shared>, found: mpsc::UnboundedSender<(H256, Bytes)>) {
+	loop {
+		let version = shared.version.load(Ordering::Acquire);
+		let job = match shared.job() {
+			Some(job) => job,
+			None => {
+				thread::sleep(IDLE_INTERVAL);
+				continue
+			},
+		};
+
+		let mut nonce = U256::from_little_endian(&rand::random::<[u8; 32]>());
+		while shared.version.load(Ordering::Acquire) == version {
+			for _ in 0..BATCH_SIZE {
+				if let Some(seal) = job.algorithm.seal(&job.pre_hash, job.difficulty, nonce) {
+					if found.send((job.pre_hash, seal.into())).is_err() {
+						return
+					}
+				}
+				nonce = nonce.overflowing_add(U256::one()).0;
+			}
+			shared.hashes.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+		}
+	}
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+	env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+	let cli = Cli::parse();
+	let client = HttpClientBuilder::default().build(&cli.url)?;
+
+	let shared = Arc::new(Shared::default());
+	let (found_tx, mut found_rx) = mpsc::unbounded_channel();
+	log::info!("⛏  Mining for {} with {} thread(s)", cli.url, cli.threads);
+	for index in 0..cli.threads {
+		let shared = shared.clone();
+		let found = found_tx.clone();
+		thread::Builder::new()
+			.name(format!("pow-miner-{}", index))
+			.spawn(move || mine(shared, found))?;
+	}
+
+	let mut poll = tokio::time::interval(Duration::from_millis(cli.poll_interval));
+	let mut report = tokio::time::interval(REPORT_INTERVAL);
+	let (mut last_hashes, mut last_time) = (0, Instant::now());
+	let mut current = None;
+	loop {
+		tokio::select! {
+			_ = poll.tick() => {
+				let fetched = client.request::<Option<Work>>("mining_getWork", rpc_params![]).await;
+				let work = match fetched {
+					Ok(work) => work,
+					Err(err) => {
+						log::warn!("Unable to fetch work: {}", err);
+						None
+					},
+				};
+				if work.as_ref().map(|work| work.pre_hash) != current {
+					let job = match work {
+						Some(work) => {
+							log::info!(
+								"New work on top of {:?} at difficulty {}",
+								work.parent,
+								work.difficulty
+							);
+							Some(Job {
+								algorithm: work.algorithm.parse()?,
+								pre_hash: work.pre_hash,
+								difficulty: work.difficulty,
+							})
+						},
+						None => None,
+					};
+					current = job.map(|job| job.pre_hash);
+					shared.set_job(job);
+				}
+			},
+			Some((pre_hash, seal)) = found_rx.recv() => {
+				// Seals for work the node no longer offers would be rejected anyway.
+				if current == Some(pre_hash) {
+					current = None;
+					shared.set_job(None);
+					let submitted = client
+						.request::<bool>("mining_submitWork", rpc_params![pre_hash, seal])
+						.await;
+					match submitted {
+						Ok(true) => log::info!("🎉 Mined block with pre-hash {:?}", pre_hash),
+						Ok(false) => log::warn!("Node did not import the block for {:?}", pre_hash),
+						Err(err) => log::warn!("Unable to submit seal: {}", err),
+					}
+				}
+			},
+			_ = report.tick() => {
+				let (hashes, now) = (shared.hashes.load(Ordering::Relaxed), Instant::now());
+				let elapsed = now.duration_since(last_time).as_secs_f64();
+				log::info!("⛏  Hashrate {:.0} H/s", (hashes - last_hashes) as f64 / elapsed);
+				last_hashes = hashes;
+				last_time = now;
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use minipow::MiniPow;
+	use sc_consensus_pow::PowAlgorithm;
+	use sp_runtime::{generic::BlockId, testing::Header, OpaqueExtrinsic};
+
+	type Block = sp_runtime::generic::Block<Header, OpaqueExtrinsic>;
+
+	/// The first seal `algorithm` finds for `pre_hash`, grinding from nonce zero.
+	fn find_seal(algorithm: Algorithm, pre_hash: &H256, difficulty: U256) -> Vec<u8> {
+		(0u64..)
+			.find_map(|nonce| algorithm.seal(pre_hash, difficulty, U256::from(nonce)))
+			.expect("A seal is found at low difficulties; qed")
+	}
+
+	#[test]
+	fn minipow_seals_are_accepted_by_the_node() {
+		let (pre_hash, difficulty) = (H256::repeat_byte(7), U256::from(64));
+		let seal = find_seal(Algorithm::MiniPow, &pre_hash, difficulty);
+
+		let pow = MiniPow::new(difficulty);
+		let parent = BlockId::<Block>::hash(H256::zero());
+		let verified = pow.verify(&parent, &pre_hash, None, &seal, difficulty);
+		assert!(verified.unwrap());
+	}
+
+	#[test]
+	fn seal_meets_difficulty_per_check_seal() {
+		let (pre_hash, difficulty) = (H256::repeat_byte(7), U256::from(64));
+		let seal = find_seal(Algorithm::Sha3, &pre_hash, difficulty);
+
+		// Only the seal primitive: `Sha3Algorithm` needs a client for its pre-digest and zone
+		// checks, which this crate does not have.
+		assert_eq!(geopow_primitives::check_seal(&pre_hash, &seal, difficulty), Ok(()));
+		assert!(geopow_primitives::check_seal(&pre_hash, &seal, U256::from(u64::MAX)).is_err());
+	}
+}
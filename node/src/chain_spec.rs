@@ -1,10 +1,11 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	opaque::SessionKeys, AccountId, BalancesConfig, DifficultyConfig, GenesisConfig,
+	GeoMiningConfig, GeoQuotasConfig, GrandpaConfig, LocationWitnessConfig, SessionConfig,
+	Signature, SudoConfig, SystemConfig, ValidatorSetConfig, HOURS, WASM_BINARY,
 };
-use sc_service::ChainType;
-use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{sr25519, Pair, Public};
+use crate::pow::{PowAlgorithmKind, POW_ALGORITHM_PROPERTY};
+use sc_service::{ChainType, Properties};
+use sp_core::{sr25519, Pair, Public, U256};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
@@ -31,9 +32,21 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
-/// Generate an Aura authority key.
-pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
-	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
+/// Chain spec properties selecting the given PoW algorithm.
+fn pow_properties(pow_algorithm: PowAlgorithmKind) -> Properties {
+	let mut properties = Properties::new();
+	properties.insert(POW_ALGORITHM_PROPERTY.into(), pow_algorithm.as_str().into());
+	properties
+}
+
+/// Generate a validator account and its GRANDPA key. Blocks are authored by whoever mines
+/// them, so finality voters are the only authorities a chain has.
+pub fn authority_keys_from_seed(s: &str) -> (AccountId, GrandpaId) {
+	(get_account_id_from_seed::<sr25519::Public>(s), get_from_seed::<GrandpaId>(s))
+}
+
+fn session_keys(grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { grandpa }
 }
 
 pub fn development_config() -> Result<ChainSpec, String> {
@@ -48,7 +61,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		move || {
 			testnet_genesis(
 				wasm_binary,
-				// Initial PoA authorities
+				// Initial validators
 				vec![authority_keys_from_seed("Alice")],
 				// Sudo account
 				get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -70,7 +83,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		None,
 		None,
 		// Properties
-		None,
+		Some(pow_properties(PowAlgorithmKind::MiniPow)),
 		// Extensions
 		None,
 	))
@@ -88,7 +101,7 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		move || {
 			testnet_genesis(
 				wasm_binary,
-				// Initial PoA authorities
+				// Initial validators
 				vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
 				// Sudo account
 				get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -116,9 +129,9 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
-		// Properties
-		None,
 		None,
+		// Properties
+		Some(pow_properties(PowAlgorithmKind::Sha3)),
 		// Extensions
 		None,
 	))
@@ -127,7 +140,7 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
-	initial_authorities: Vec<(AuraId, GrandpaId)>,
+	initial_authorities: Vec<(AccountId, GrandpaId)>,
 	root_key: AccountId,
 	endowed_accounts: Vec<AccountId>,
 	_enable_println: bool,
@@ -141,16 +154,56 @@ fn testnet_genesis(
 			// Configure endowed accounts with initial balance of 1 << 60.
 			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
 		},
-		aura: AuraConfig {
-			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
+		validator_set: ValidatorSetConfig {
+			// Bonded from the endowments above.
+			validators: initial_authorities.iter().map(|x| x.0.clone()).collect(),
 		},
-		grandpa: GrandpaConfig {
-			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect(),
+		session: SessionConfig {
+			keys: initial_authorities
+				.iter()
+				.map(|x| (x.0.clone(), x.0.clone(), session_keys(x.1.clone())))
+				.collect(),
 		},
+		// The session pallet hands the validator set's keys to GRANDPA.
+		grandpa: GrandpaConfig { authorities: vec![] },
 		sudo: SudoConfig {
 			// Assign network admin rights.
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		difficulty: DifficultyConfig {
+			// Low enough for a single CPU miner to find blocks at the target block time.
+			initial_difficulty: U256::from(200_000),
+		},
+		geo_mining: GeoMiningConfig {
+			// One cell per degree with a bit under a quarter of the globe inside the zone.
+			zone_params: Default::default(),
+		},
+		location_witness: LocationWitnessConfig {
+			// Attestations are not required until governance registers witnesses.
+			witnesses: vec![],
+			min_witnesses: 0,
+			max_attestation_age: HOURS,
+		},
+		geo_quotas: GeoQuotasConfig {
+			// Miners are not limited until governance defines regions and their quotas.
+			regions: vec![],
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::BuildStorage;
+
+	#[test]
+	fn development_genesis_builds() {
+		development_config().unwrap().build_storage().unwrap();
+	}
+
+	#[test]
+	fn local_testnet_genesis_builds() {
+		local_testnet_config().unwrap().build_storage().unwrap();
 	}
 }
@@ -1,4 +1,13 @@
+use crate::pow::PowParams;
+use node_template_runtime::AccountId;
 use sc_cli::RunCmd;
+use sha3pow::Attestation;
+use sp_core::Decode;
+use std::path::PathBuf;
+#[cfg(feature = "stratum")]
+use crate::stratum::StratumConfig;
+#[cfg(feature = "stratum")]
+use std::net::SocketAddr;
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +16,91 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	#[clap(flatten)]
+	pub pow: PowParams,
+
+	#[clap(flatten)]
+	pub mining: MiningParams,
+}
+
+/// Parameters for the local PoW mining worker.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct MiningParams {
+	/// Build and mine blocks on this node.
+	///
+	/// Without it, or `--author`, the node only follows the chain and never proposes blocks.
+	/// Mining requires the node to run as an authority, e.g. with `--validator`.
+	#[clap(long)]
+	pub mine: bool,
+
+	/// Account credited as the author of blocks mined by this node (SS58 or hex).
+	///
+	/// Implies `--mine`.
+	#[clap(long, value_name = "ACCOUNT")]
+	pub author: Option<AccountId>,
+
+	/// IP address this node claims as its location in the blocks it mines.
+	#[clap(long, value_name = "IP", default_value = "127.0.0.1")]
+	pub miner_ip: String,
+
+	/// File with the witness attestations backing the location claim.
+	///
+	/// Holds a hex-encoded SCALE `Vec<Attestation>`. It is read once at startup, so the node
+	/// must be restarted with fresh attestations before the old ones expire.
+	#[clap(long, value_name = "PATH")]
+	pub attestations: Option<PathBuf>,
+
+	/// Number of CPU threads grinding nonces for the blocks this node builds.
+	///
+	/// Each thread searches its own slice of the nonce space. Use 0 to build block templates
+	/// without mining them locally.
+	#[clap(long, value_name = "COUNT", default_value = "1")]
+	pub mining_threads: usize,
+
+	/// Serve pooled miners with a Stratum-like protocol on this address, e.g. `0.0.0.0:3333`.
+	///
+	/// Only used together with `--mine`.
+	#[cfg(feature = "stratum")]
+	#[clap(long, value_name = "ADDR")]
+	pub stratum: Option<SocketAddr>,
+
+	/// Difficulty the shares of Stratum miners have to meet.
+	///
+	/// Keep it well below the block difficulty, so that every miner hands in shares regularly.
+	#[cfg(feature = "stratum")]
+	#[clap(long, value_name = "DIFFICULTY", default_value = "1000")]
+	pub stratum_difficulty: u64,
+}
+
+impl MiningParams {
+	/// Whether this node was asked to mine.
+	pub fn enabled(&self) -> bool {
+		self.mine || self.author.is_some()
+	}
+
+	/// The Stratum server configuration, if one was asked for.
+	#[cfg(feature = "stratum")]
+	pub fn stratum(&self) -> Option<StratumConfig> {
+		self.stratum.map(|listen| StratumConfig {
+			listen,
+			share_difficulty: self.stratum_difficulty.into(),
+		})
+	}
+
+	/// Load the attestations file, if one was given.
+	pub fn attestations(&self) -> Result<Vec<Attestation>, String> {
+		let path = match &self.attestations {
+			Some(path) => path,
+			None => return Ok(Vec::new()),
+		};
+		let contents = std::fs::read_to_string(path)
+			.map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+		let bytes = sp_core::bytes::from_hex(contents.trim())
+			.map_err(|err| format!("Invalid hex in {}: {}", path.display(), err))?;
+		Vec::<Attestation>::decode(&mut &bytes[..])
+			.map_err(|err| format!("Invalid attestations in {}: {}", path.display(), err))
+	}
 }
 
 #[derive(Debug, clap::Subcommand)]
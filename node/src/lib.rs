@@ -1,3 +1,9 @@
 pub mod chain_spec;
+pub mod metrics;
+pub mod miner;
+pub mod pow;
 pub mod rpc;
+pub mod select_chain;
 pub mod service;
+#[cfg(feature = "stratum")]
+pub mod stratum;
@@ -7,7 +7,13 @@ mod service;
 mod cli;
 mod command;
 mod command_helper;
+mod metrics;
+mod miner;
+mod pow;
 mod rpc;
+mod select_chain;
+#[cfg(feature = "stratum")]
+mod stratum;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
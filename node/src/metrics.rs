@@ -0,0 +1,160 @@
+//! Prometheus metrics of the PoW consensus and the local miner.
+
+use crate::{miner::MinerStats, pow::NodePowAlgorithm, service::FullClient};
+use futures::{FutureExt, StreamExt};
+use futures_timer::Delay;
+use geopow_consensus::VerifyDetailed;
+use node_template_runtime::opaque::Block;
+use sc_client_api::BlockchainEvents;
+use sc_consensus_pow::{Error, PowAlgorithm};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_pow::Seal;
+use sp_core::{H256, U256};
+use sp_runtime::generic::BlockId;
+use std::{
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use substrate_prometheus_endpoint::{
+	register, Counter, CounterVec, Gauge, Opts, PrometheusError, Registry, U64,
+};
+
+/// How often the gauges are refreshed.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// PoW metrics.
+#[derive(Clone)]
+pub struct PowMetrics {
+	hashrate: Gauge<U64>,
+	nonces_tried: Counter<U64>,
+	seals_found: Counter<U64>,
+	seals_rejected: CounterVec<U64>,
+	difficulty: Gauge<U64>,
+	seconds_since_last_block: Gauge<U64>,
+}
+
+impl PowMetrics {
+	/// Register the metrics with `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			hashrate: register(
+				Gauge::new("pow_hashrate", "Hashes per second of the local miner threads")?,
+				registry,
+			)?,
+			nonces_tried: register(
+				Counter::new("pow_nonces_tried_total", "Nonces tried by the local miner threads")?,
+				registry,
+			)?,
+			seals_found: register(
+				Counter::new("pow_seals_found_total", "Blocks sealed by the local miner threads")?,
+				registry,
+			)?,
+			seals_rejected: register(
+				CounterVec::new(
+					Opts::new("pow_seals_rejected_total", "Block seals rejected on import"),
+					&["reason"],
+				)?,
+				registry,
+			)?,
+			difficulty: register(
+				Gauge::new(
+					"pow_difficulty",
+					"Difficulty of blocks on top of the best block, saturated at u64::MAX",
+				)?,
+				registry,
+			)?,
+			seconds_since_last_block: register(
+				Gauge::new("pow_seconds_since_last_block", "Seconds since the last best block")?,
+				registry,
+			)?,
+		})
+	}
+
+	fn set_difficulty(&self, algorithm: &NodePowAlgorithm, best_hash: H256) {
+		match algorithm.difficulty(best_hash) {
+			Ok(difficulty) => self.difficulty.set(difficulty.min(U256::from(u64::MAX)).low_u64()),
+			Err(err) => log::debug!("Unable to read difficulty for metrics: {}", err),
+		}
+	}
+}
+
+/// [`NodePowAlgorithm`] logging and counting the seals it rejects, by reason. Block import
+/// verifies through it, while the mining worker keeps using the plain algorithm.
+#[derive(Clone)]
+pub struct MeteredPow {
+	algorithm: NodePowAlgorithm,
+	metrics: Option<PowMetrics>,
+}
+
+impl MeteredPow {
+	/// Wrap `algorithm`, counting into `metrics` if Prometheus is enabled.
+	pub fn new(algorithm: NodePowAlgorithm, metrics: Option<PowMetrics>) -> Self {
+		Self { algorithm, metrics }
+	}
+}
+
+impl PowAlgorithm<Block> for MeteredPow {
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: H256) -> Result<Self::Difficulty, Error<Block>> {
+		self.algorithm.difficulty(parent)
+	}
+
+	fn verify(
+		&self,
+		parent: &BlockId<Block>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &Seal,
+		difficulty: Self::Difficulty,
+	) -> Result<bool, Error<Block>> {
+		let verified =
+			self.algorithm.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty)?;
+		if let Err(reason) = &verified {
+			// Peers can send invalid seals at will, the metric is the place to watch them.
+			log::debug!(target: "pow", "Rejected block with pre-hash {:?}: {}", pre_hash, reason);
+			if let Some(metrics) = &self.metrics {
+				metrics.seals_rejected.with_label_values(&[reason.as_str()]).inc();
+			}
+		}
+		Ok(verified.is_ok())
+	}
+}
+
+/// Keep the difficulty, time since the last block and, on mining nodes, the miner thread
+/// metrics up to date, until the client shuts down.
+pub async fn run(
+	metrics: PowMetrics,
+	client: Arc<FullClient>,
+	algorithm: NodePowAlgorithm,
+	stats: Option<Arc<MinerStats>>,
+) {
+	let mut imports = client.import_notification_stream().fuse();
+	let mut last_block = Instant::now();
+	let (mut hashes, mut blocks_found) = (0, 0);
+	metrics.set_difficulty(&algorithm, client.info().best_hash);
+
+	loop {
+		futures::select! {
+			notification = imports.next() => match notification {
+				Some(notification) if notification.is_new_best => {
+					last_block = Instant::now();
+					metrics.set_difficulty(&algorithm, notification.hash);
+				},
+				Some(_) => {},
+				None => return,
+			},
+			_ = Delay::new(UPDATE_INTERVAL).fuse() => {},
+		}
+
+		metrics.seconds_since_last_block.set(last_block.elapsed().as_secs());
+		if let Some(stats) = &stats {
+			let (now_hashes, now_found) = (stats.hashes(), stats.blocks_found());
+			metrics.nonces_tried.inc_by(now_hashes - hashes);
+			metrics.seals_found.inc_by(now_found - blocks_found);
+			metrics.hashrate.set(stats.hashrate());
+			hashes = now_hashes;
+			blocks_found = now_found;
+		}
+	}
+}
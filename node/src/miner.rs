@@ -0,0 +1,214 @@
+//! Multi-threaded CPU miner grinding nonces for the PoW mining worker.
+//!
+//! `sc_consensus_pow::start_mining_worker` only builds block templates; finding a seal is
+//! left to whoever holds the [`MiningHandle`]. The miner threads started here poll the
+//! handle for the current template, each searching its own slice of the `U256` nonce space,
+//! and submit the first seal that meets the difficulty.
+
+use crate::pow::NodePowAlgorithm;
+use node_template_runtime::opaque::Block;
+use sc_consensus::JustificationSyncLink;
+use sc_consensus_pow::{MiningHandle, MiningMetadata};
+use sp_api::{ProvideRuntimeApi, TransactionFor};
+use sp_core::{H256, U256};
+use std::{
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// Number of nonces tried between checks for a new block template.
+const BATCH_SIZE: u64 = 10_000;
+
+/// How long an idle thread waits before asking for a template again.
+const IDLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the hashrate is logged.
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Counters shared by all miner threads.
+#[derive(Default, Debug)]
+pub struct MinerStats {
+	hashes: AtomicU64,
+	blocks_found: AtomicU64,
+	hashrate: AtomicU64,
+}
+
+impl MinerStats {
+	/// Total number of nonces tried.
+	pub fn hashes(&self) -> u64 {
+		self.hashes.load(Ordering::Relaxed)
+	}
+
+	/// Number of seals found and accepted by the mining worker.
+	pub fn blocks_found(&self) -> u64 {
+		self.blocks_found.load(Ordering::Relaxed)
+	}
+
+	/// Hashes per second over the last report interval.
+	pub fn hashrate(&self) -> u64 {
+		self.hashrate.load(Ordering::Relaxed)
+	}
+}
+
+/// First nonce of the slice of the nonce space searched by thread `index` of `threads`.
+fn partition_start(index: usize, threads: usize) -> U256 {
+	U256::MAX / U256::from(threads) * U256::from(index)
+}
+
+/// Start `threads` miner threads and a thread reporting their hashrate.
+pub fn start<C, L, Proof>(
+	handle: MiningHandle<Block, NodePowAlgorithm, C, L, Proof>,
+	algorithm: NodePowAlgorithm,
+	threads: usize,
+) -> Arc<MinerStats>
+where
+	C: ProvideRuntimeApi<Block> + 'static,
+	L: JustificationSyncLink<Block> + 'static,
+	Proof: Send + 'static,
+	TransactionFor<C, Block>: Send + 'static,
+	MiningHandle<Block, NodePowAlgorithm, C, L, Proof>: Send,
+{
+	let stats = Arc::new(MinerStats::default());
+	log::info!("⛏  Starting {} CPU miner thread(s)", threads);
+
+	for index in 0..threads {
+		let handle = handle.clone();
+		let algorithm = algorithm.clone();
+		let stats = stats.clone();
+		thread::Builder::new()
+			.name(format!("pow-miner-{}", index))
+			.spawn(move || mine(handle, algorithm, partition_start(index, threads), stats))
+			.expect("Spawning miner threads only fails if the OS is out of resources; qed");
+	}
+
+	let reported = stats.clone();
+	thread::Builder::new()
+		.name("pow-miner-stats".into())
+		.spawn(move || report(reported))
+		.expect("Spawning miner threads only fails if the OS is out of resources; qed");
+
+	stats
+}
+
+/// Grind nonces from `start` onwards on whatever template the worker currently offers. The
+/// nonce cursor carries over from one template to the next, so a seal the worker rejected
+/// is never found and submitted again.
+fn mine<C, L, Proof>(
+	handle: MiningHandle<Block, NodePowAlgorithm, C, L, Proof>,
+	algorithm: NodePowAlgorithm,
+	start: U256,
+	stats: Arc<MinerStats>,
+) where
+	C: ProvideRuntimeApi<Block>,
+	L: JustificationSyncLink<Block>,
+	TransactionFor<C, Block>: Send + 'static,
+{
+	let mut nonce = start;
+	loop {
+		let version = handle.version();
+		let MiningMetadata { pre_hash, difficulty, .. } = match handle.metadata() {
+			Some(metadata) => metadata,
+			None => {
+				thread::sleep(IDLE_INTERVAL);
+				continue
+			},
+		};
+
+		let stale = || handle.version() != version;
+		let (seal, next) = search(&algorithm, &pre_hash, difficulty, nonce, &stats, stale);
+		nonce = next;
+		if let Some(seal) = seal {
+			if futures::executor::block_on(handle.submit(seal)) {
+				stats.blocks_found.fetch_add(1, Ordering::Relaxed);
+			} else {
+				log::debug!("Mining worker rejected seal for {:?}", pre_hash);
+				// The template cannot be sealed anymore, wait for the next one.
+				while handle.version() == version {
+					thread::sleep(IDLE_INTERVAL);
+				}
+			}
+		}
+	}
+}
+
+/// Try nonces from `start` onwards in batches until one meets `difficulty` or `stale`
+/// reports that the template changed. Returns the seal, if any, and the nonce to resume
+/// searching from.
+fn search(
+	algorithm: &NodePowAlgorithm,
+	pre_hash: &H256,
+	difficulty: U256,
+	start: U256,
+	stats: &MinerStats,
+	stale: impl Fn() -> bool,
+) -> (Option<Vec<u8>>, U256) {
+	let mut nonce = start;
+	loop {
+		for tried in 1..=BATCH_SIZE {
+			let seal = algorithm.seal(pre_hash, difficulty, nonce);
+			nonce = nonce.overflowing_add(U256::one()).0;
+			if seal.is_some() {
+				stats.hashes.fetch_add(tried, Ordering::Relaxed);
+				return (seal, nonce)
+			}
+		}
+		stats.hashes.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+		if stale() {
+			return (None, nonce)
+		}
+	}
+}
+
+/// Log the hashrate over the last interval, forever.
+fn report(stats: Arc<MinerStats>) {
+	let (mut last_hashes, mut last_time) = (stats.hashes(), Instant::now());
+	loop {
+		thread::sleep(REPORT_INTERVAL);
+		let (hashes, now) = (stats.hashes(), Instant::now());
+		let rate = (hashes - last_hashes) as f64 / now.duration_since(last_time).as_secs_f64();
+		stats.hashrate.store(rate as u64, Ordering::Relaxed);
+		log::info!("⛏  Hashrate {:.0} H/s, {} block(s) found", rate, stats.blocks_found());
+		last_hashes = hashes;
+		last_time = now;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use minipow::MiniPow;
+
+	#[test]
+	fn search_resumes_after_the_last_seal() {
+		let difficulty = U256::from(16);
+		let algorithm = NodePowAlgorithm::MiniPow(MiniPow::new(difficulty));
+		let (pre_hash, stats) = (H256::repeat_byte(7), MinerStats::default());
+		let search_from =
+			|start| search(&algorithm, &pre_hash, difficulty, start, &stats, || false);
+
+		let (first, next) = search_from(U256::zero());
+		let (second, after) = search_from(next);
+		assert!(first.is_some() && second.is_some());
+		// The second search starts after the nonce of the first seal, so it cannot find
+		// the same seal again.
+		assert_ne!(first, second);
+		assert!(next < after);
+		// Every nonce was tried exactly once.
+		assert_eq!(stats.hashes(), after.low_u64());
+	}
+
+	#[test]
+	fn search_stops_on_stale_templates() {
+		// No nonce meets the highest difficulty.
+		let algorithm = NodePowAlgorithm::MiniPow(MiniPow::new(U256::MAX));
+		let stats = MinerStats::default();
+		let (seal, next) =
+			search(&algorithm, &H256::zero(), U256::MAX, U256::from(5), &stats, || true);
+		assert_eq!(seal, None);
+		assert_eq!(next, U256::from(5 + BATCH_SIZE));
+	}
+}
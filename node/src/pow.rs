@@ -0,0 +1,266 @@
+//! Selection of the proof-of-work algorithm the node seals and verifies blocks with.
+
+use crate::service::FullClient;
+use geopow_consensus::VerifyDetailed;
+use minipow::MiniPow;
+use node_template_runtime::opaque::Block;
+use sc_consensus_pow::{Error, PowAlgorithm};
+use sc_service::ChainSpec;
+use sha3pow::{
+	hash_meets_difficulty, Compute, CsvRangeLocator, GeoLocator, SealError, Sha3Algorithm,
+	StubLocator,
+};
+use sp_consensus_pow::Seal;
+use sp_core::{Encode, H256, U256};
+use sp_runtime::generic::BlockId;
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
+
+/// Name of the chain spec property that selects the PoW algorithm.
+pub const POW_ALGORITHM_PROPERTY: &str = "powAlgorithm";
+
+/// Command line parameters selecting and configuring the PoW algorithm.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct PowParams {
+	/// Proof-of-work algorithm used to seal and verify blocks.
+	///
+	/// Overrides the chain spec's `powAlgorithm` property. Chains that set neither use minipow.
+	#[clap(long, arg_enum, value_name = "ALGORITHM")]
+	pub pow_algorithm: Option<PowAlgorithmKind>,
+
+	/// GeoIP database used to locate miners for the sha3-geo algorithm.
+	///
+	/// A CSV table with one `start,end,latitude,longitude` IP range per line. Every node of
+	/// a network must use the same table. Without it, miners are placed with a deterministic
+	/// stub that is only suitable for development chains.
+	#[clap(long, value_name = "PATH")]
+	pub geoip_db: Option<PathBuf>,
+
+	/// Fixed difficulty of the minipow algorithm.
+	///
+	/// A nonce is accepted with a probability of one in DIFFICULTY. Every node of a network
+	/// must use the same value.
+	#[clap(long, value_name = "DIFFICULTY", default_value_t = MiniPow::DEFAULT_DIFFICULTY)]
+	pub minipow_difficulty: u64,
+}
+
+impl PowParams {
+	/// Load the configured locator, falling back to the stub.
+	pub fn locator(&self) -> Result<Arc<dyn GeoLocator>, String> {
+		match &self.geoip_db {
+			Some(path) => {
+				let locator = CsvRangeLocator::open(path).map_err(|err| err.to_string())?;
+				log::info!("🌍 Loaded {} GeoIP ranges from {}", locator.len(), path.display());
+				Ok(Arc::new(locator))
+			},
+			None => Ok(Arc::new(StubLocator)),
+		}
+	}
+
+	/// Resolve the algorithm for the given chain and instantiate it.
+	pub fn build(
+		&self,
+		chain_spec: &dyn ChainSpec,
+		client: Arc<FullClient>,
+	) -> Result<NodePowAlgorithm, String> {
+		let kind = PowAlgorithmKind::resolve(self.pow_algorithm, chain_spec)?;
+		log::info!("⛏  Sealing and verifying blocks with {} proof of work", kind);
+
+		Ok(match kind {
+			PowAlgorithmKind::MiniPow =>
+				NodePowAlgorithm::MiniPow(MiniPow::new(U256::from(self.minipow_difficulty))),
+			PowAlgorithmKind::Sha3 => NodePowAlgorithm::Sha3(Sha3Algorithm::new(client)),
+			PowAlgorithmKind::Sha3Geo => {
+				if self.geoip_db.is_none() {
+					log::warn!("No --geoip-db given, locating miners with the development stub");
+				}
+				NodePowAlgorithm::Sha3(Sha3Algorithm::with_mining_zone(client, self.locator()?))
+			},
+		})
+	}
+}
+
+/// The PoW algorithms a node can be started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum PowAlgorithmKind {
+	/// Cheap xxHash64 PoW with a fixed difficulty, for development chains.
+	#[clap(name = "minipow")]
+	MiniPow,
+	/// Sha3 PoW with the difficulty taken from the runtime.
+	#[clap(name = "sha3")]
+	Sha3,
+	/// Sha3 PoW that also requires the miner to be inside the mining zone.
+	#[clap(name = "sha3-geo")]
+	Sha3Geo,
+}
+
+impl PowAlgorithmKind {
+	/// The name used on the command line and in chain specs.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::MiniPow => "minipow",
+			Self::Sha3 => "sha3",
+			Self::Sha3Geo => "sha3-geo",
+		}
+	}
+
+	/// Pick the algorithm to run with. An explicit command line choice wins over the chain
+	/// spec property; chains that specify neither use MiniPow.
+	pub fn resolve(cli: Option<Self>, chain_spec: &dyn ChainSpec) -> Result<Self, String> {
+		if let Some(kind) = cli {
+			return Ok(kind)
+		}
+
+		match chain_spec.properties().get(POW_ALGORITHM_PROPERTY) {
+			None => Ok(Self::MiniPow),
+			Some(value) => value
+				.as_str()
+				.ok_or_else(|| format!("`{}` property must be a string", POW_ALGORITHM_PROPERTY))?
+				.parse(),
+		}
+	}
+}
+
+impl FromStr for PowAlgorithmKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"minipow" => Ok(Self::MiniPow),
+			"sha3" => Ok(Self::Sha3),
+			"sha3-geo" => Ok(Self::Sha3Geo),
+			other => Err(format!("Unknown PoW algorithm `{}`", other)),
+		}
+	}
+}
+
+impl fmt::Display for PowAlgorithmKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// The PoW algorithm chosen at startup. `PowBlockImport`, the import queue and the mining
+/// worker are generic over a single algorithm type, so the choice is made by dispatching
+/// on this enum rather than on the type level.
+#[derive(Clone)]
+pub enum NodePowAlgorithm {
+	/// See [`PowAlgorithmKind::MiniPow`].
+	MiniPow(MiniPow),
+	/// See [`PowAlgorithmKind::Sha3`] and [`PowAlgorithmKind::Sha3Geo`].
+	Sha3(Sha3Algorithm<FullClient>),
+}
+
+impl NodePowAlgorithm {
+	/// The algorithm seals have to be computed with. Sha3 and sha3-geo seals are alike, the
+	/// mining zone only constrains the pre-runtime digest.
+	pub fn seal_kind(&self) -> PowAlgorithmKind {
+		match self {
+			Self::MiniPow(_) => PowAlgorithmKind::MiniPow,
+			Self::Sha3(_) => PowAlgorithmKind::Sha3,
+		}
+	}
+
+	/// The locator the mining zone is enforced with, if the algorithm enforces one.
+	pub fn locator(&self) -> Option<Arc<dyn GeoLocator>> {
+		match self {
+			Self::MiniPow(_) => None,
+			Self::Sha3(algorithm) => algorithm.locator().cloned(),
+		}
+	}
+
+	/// Seal `pre_hash` with `nonce` if the resulting work meets `difficulty`. This is the
+	/// inner loop of the CPU miner.
+	pub fn seal(&self, pre_hash: &H256, difficulty: U256, nonce: U256) -> Option<Seal> {
+		match self {
+			Self::MiniPow(algorithm) => algorithm.seal(pre_hash.as_bytes(), nonce, difficulty),
+			Self::Sha3(_) => {
+				let seal = Compute { difficulty, pre_hash: *pre_hash, nonce }.compute();
+				hash_meets_difficulty(&seal.work, difficulty).then(|| seal.encode())
+			},
+		}
+	}
+
+	/// Grade a pooled miner's `nonce` for `pre_hash`: whether its work meets the pool's
+	/// `share_difficulty` and, if it even meets the block `difficulty`, the seal to submit.
+	pub fn grade(
+		&self,
+		pre_hash: &H256,
+		difficulty: U256,
+		share_difficulty: U256,
+		nonce: U256,
+	) -> Share {
+		match self {
+			Self::MiniPow(algorithm) => {
+				if let Some(seal) = algorithm.seal(pre_hash.as_bytes(), nonce, difficulty) {
+					Share::Block(seal)
+				} else if algorithm.seal(pre_hash.as_bytes(), nonce, share_difficulty).is_some() {
+					Share::Valid
+				} else {
+					Share::Invalid
+				}
+			},
+			Self::Sha3(_) => {
+				// The seal commits to the block difficulty, so shares are computed with it too.
+				let seal = Compute { difficulty, pre_hash: *pre_hash, nonce }.compute();
+				if hash_meets_difficulty(&seal.work, difficulty) {
+					Share::Block(seal.encode())
+				} else if hash_meets_difficulty(&seal.work, share_difficulty) {
+					Share::Valid
+				} else {
+					Share::Invalid
+				}
+			},
+		}
+	}
+}
+
+/// How a nonce fares against the share and block difficulties, see [`NodePowAlgorithm::grade`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Share {
+	/// The work meets neither difficulty.
+	Invalid,
+	/// The work meets the share difficulty only.
+	Valid,
+	/// The work meets the block difficulty, the block can be sealed with this seal.
+	Block(Seal),
+}
+
+impl PowAlgorithm<Block> for NodePowAlgorithm {
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: H256) -> Result<Self::Difficulty, Error<Block>> {
+		match self {
+			Self::MiniPow(algorithm) => PowAlgorithm::<Block>::difficulty(algorithm, parent),
+			Self::Sha3(algorithm) => PowAlgorithm::<Block>::difficulty(algorithm, parent),
+		}
+	}
+
+	fn verify(
+		&self,
+		parent: &BlockId<Block>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &Seal,
+		difficulty: Self::Difficulty,
+	) -> Result<bool, Error<Block>> {
+		Ok(self.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty)?.is_ok())
+	}
+}
+
+impl VerifyDetailed<Block> for NodePowAlgorithm {
+	fn verify_detailed(
+		&self,
+		parent: &BlockId<Block>,
+		pre_hash: &H256,
+		pre_digest: Option<&[u8]>,
+		seal: &Seal,
+		difficulty: Self::Difficulty,
+	) -> Result<Result<(), SealError>, Error<Block>> {
+		match self {
+			Self::MiniPow(algorithm) =>
+				algorithm.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty),
+			Self::Sha3(algorithm) =>
+				algorithm.verify_detailed(parent, pre_hash, pre_digest, seal, difficulty),
+		}
+	}
+}
@@ -13,9 +13,13 @@ use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sha3pow::GeoLocator;
 
 pub use sc_rpc_api::DenyUnsafe;
 
+pub mod mining;
+pub mod mining_zone;
+
 /// Full client dependencies.
 pub struct FullDeps<C, P> {
 	/// The client instance to use.
@@ -24,6 +28,10 @@ pub struct FullDeps<C, P> {
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Locator used to answer mining zone queries by IP address.
+	pub geo_locator: Arc<dyn GeoLocator>,
+	/// The local miner, whose status and work are served over RPC.
+	pub miner: mining::LocalMiner<AccountId>,
 }
 
 /// Instantiate all full RPC extensions.
@@ -38,17 +46,23 @@ where
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber, Hash>,
 	C::Api: BlockBuilder<Block>,
+	C::Api: sha3pow::GeoMiningApi<Block>,
 	P: TransactionPool + 'static,
 {
 	use pallet_transaction_payment_rpc::{TransactionPaymentApiServer, TransactionPaymentRpc};
 	use substrate_frame_rpc_system::{SystemApiServer, SystemRpc};
 	use pallet_contracts_rpc::{ContractsApiServer, ContractsRpc};
+	use mining_zone::{MiningZone, MiningZoneApiServer};
+	use mining::{MiningApiServer, MiningStatusApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, geo_locator, miner } = deps;
 
 	module.merge(SystemRpc::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
 	module.merge(TransactionPaymentRpc::new(client.clone()).into_rpc())?;
+	module.merge(MiningZone::new(client.clone(), geo_locator, deny_unsafe).into_rpc())?;
+	module.merge(MiningStatusApiServer::into_rpc(miner.clone()))?;
+	module.merge(MiningApiServer::into_rpc(miner))?;
 	module.merge(ContractsRpc::new(client).into_rpc())?;
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
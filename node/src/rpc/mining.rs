@@ -0,0 +1,223 @@
+//! RPC methods reporting on the local miner, so that operators can tell mining nodes from
+//! nodes that only follow the chain, and handing out work to external miner processes.
+
+use crate::{miner::MinerStats, pow::NodePowAlgorithm};
+use futures::{future::BoxFuture, FutureExt};
+use geopow_consensus::VerifyDetailed;
+use jsonrpsee::{
+	core::{async_trait, Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use node_template_runtime::opaque::Block;
+use sc_consensus::JustificationSyncLink;
+use sc_consensus_pow::{MiningHandle, MiningMetadata};
+use serde::{Deserialize, Serialize};
+use sp_api::{ProvideRuntimeApi, TransactionFor};
+use sp_consensus_pow::Seal;
+use sp_core::{Bytes, H256, U256};
+use sp_runtime::generic::BlockId;
+use std::sync::Arc;
+
+/// Whether and how this node mines.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningStatus<AccountId> {
+	/// Whether the node builds blocks and offers them for mining.
+	pub mining: bool,
+	/// Account credited as the author of mined blocks.
+	pub author: Option<AccountId>,
+	/// Number of local CPU miner threads.
+	pub threads: usize,
+	/// Hashes per second of the local miner threads over the last report interval.
+	pub hashrate: u64,
+	/// Total number of nonces tried by the local miner threads.
+	pub hashes: u64,
+	/// Number of blocks mined by the local miner threads.
+	pub blocks_found: u64,
+}
+
+/// The block template currently offered for mining.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work<Hash> {
+	/// Algorithm the seal has to be computed with, `minipow` or `sha3`.
+	pub algorithm: String,
+	/// The block the template builds on.
+	pub parent: Hash,
+	/// Hash of the template's header without the seal.
+	pub pre_hash: Hash,
+	/// Difficulty the seal has to meet.
+	pub difficulty: U256,
+}
+
+/// Mining status RPC methods.
+#[rpc(server)]
+pub trait MiningStatusApi<AccountId> {
+	/// Whether this node mines, for which author and how fast.
+	#[method(name = "system_miningStatus")]
+	fn mining_status(&self) -> RpcResult<MiningStatus<AccountId>>;
+}
+
+/// RPC methods for miners running as separate processes, in the spirit of `eth_getWork`
+/// and `eth_submitWork`.
+#[rpc(server)]
+pub trait MiningApi<Hash> {
+	/// The current block template, or `null` while the node has none, e.g. while syncing.
+	#[method(name = "mining_getWork")]
+	fn get_work(&self) -> RpcResult<Option<Work<Hash>>>;
+
+	/// Submit a SCALE encoded seal for the template with `pre_hash`. Returns whether the
+	/// sealed block was imported.
+	#[method(name = "mining_submitWork")]
+	async fn submit_work(&self, pre_hash: Hash, seal: Bytes) -> RpcResult<bool>;
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The node does not mine.
+	NotMining,
+	/// The submission is for a template that is no longer offered.
+	StaleWork,
+	/// The seal was rejected.
+	InvalidSeal,
+	/// The seal could not be verified.
+	VerificationFailed,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::NotMining => 1,
+			Error::StaleWork => 2,
+			Error::InvalidSeal => 3,
+			Error::VerificationFailed => 4,
+		}
+	}
+}
+
+fn error(code: Error, message: &str, data: Option<String>) -> JsonRpseeError {
+	CallError::Custom(ErrorObject::owned(code.into(), message, data)).into()
+}
+
+/// The mining worker as seen by the RPC, hiding the type parameters of its [`MiningHandle`].
+pub trait MiningWorker: Send + Sync {
+	/// The template currently offered for mining.
+	fn metadata(&self) -> Option<MiningMetadata<H256, U256>>;
+
+	/// Seal the current template and import it. Resolves to whether that succeeded.
+	fn submit(&self, seal: Seal) -> BoxFuture<'static, bool>;
+}
+
+impl<C, L, Proof> MiningWorker for MiningHandle<Block, NodePowAlgorithm, C, L, Proof>
+where
+	C: ProvideRuntimeApi<Block> + 'static,
+	L: JustificationSyncLink<Block> + 'static,
+	Proof: Send + 'static,
+	TransactionFor<C, Block>: Send + 'static,
+	MiningHandle<Block, NodePowAlgorithm, C, L, Proof>: Send + Sync,
+{
+	fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+		MiningHandle::metadata(self)
+	}
+
+	fn submit(&self, seal: Seal) -> BoxFuture<'static, bool> {
+		let handle = self.clone();
+		async move { handle.submit(seal).await }.boxed()
+	}
+}
+
+/// The local miner, as started by the service. Implements [`MiningStatusApiServer`] and
+/// [`MiningApiServer`].
+#[derive(Clone)]
+pub struct LocalMiner<AccountId> {
+	author: Option<AccountId>,
+	threads: usize,
+	stats: Option<Arc<MinerStats>>,
+	worker: Option<(Arc<dyn MiningWorker>, NodePowAlgorithm)>,
+}
+
+impl<AccountId> LocalMiner<AccountId> {
+	/// A node that does not mine.
+	pub fn disabled() -> Self {
+		Self { author: None, threads: 0, stats: None, worker: None }
+	}
+
+	/// A node mining for `author` with `worker`, whose templates are sealed with `algorithm`.
+	/// The `threads` local miner threads count into `stats`.
+	pub fn new(
+		author: Option<AccountId>,
+		worker: Arc<dyn MiningWorker>,
+		algorithm: NodePowAlgorithm,
+		threads: usize,
+		stats: Option<Arc<MinerStats>>,
+	) -> Self {
+		Self { author, threads, stats, worker: Some((worker, algorithm)) }
+	}
+
+	fn worker(&self) -> RpcResult<&(Arc<dyn MiningWorker>, NodePowAlgorithm)> {
+		self.worker.as_ref().ok_or_else(|| {
+			error(Error::NotMining, "Node is not mining, start it with --mine.", None)
+		})
+	}
+}
+
+impl<AccountId> MiningStatusApiServer<AccountId> for LocalMiner<AccountId>
+where
+	AccountId: Clone + Serialize + Send + Sync + 'static,
+{
+	fn mining_status(&self) -> RpcResult<MiningStatus<AccountId>> {
+		let stats = self.stats.as_deref();
+		Ok(MiningStatus {
+			mining: self.worker.is_some(),
+			author: self.author.clone(),
+			threads: self.threads,
+			hashrate: stats.map_or(0, MinerStats::hashrate),
+			hashes: stats.map_or(0, MinerStats::hashes),
+			blocks_found: stats.map_or(0, MinerStats::blocks_found),
+		})
+	}
+}
+
+#[async_trait]
+impl<AccountId> MiningApiServer<H256> for LocalMiner<AccountId>
+where
+	AccountId: Clone + Send + Sync + 'static,
+{
+	fn get_work(&self) -> RpcResult<Option<Work<H256>>> {
+		let (worker, algorithm) = self.worker()?;
+		Ok(worker.metadata().map(|metadata| Work {
+			algorithm: algorithm.seal_kind().to_string(),
+			parent: metadata.best_hash,
+			pre_hash: metadata.pre_hash,
+			difficulty: metadata.difficulty,
+		}))
+	}
+
+	async fn submit_work(&self, pre_hash: H256, seal: Bytes) -> RpcResult<bool> {
+		let (worker, algorithm) = self.worker()?;
+		let metadata = match worker.metadata() {
+			Some(metadata) if metadata.pre_hash == pre_hash => metadata,
+			_ => return Err(error(Error::StaleWork, "Work is no longer offered.", None)),
+		};
+
+		// Check the seal here, so that miners learn about bad seals instead of the worker
+		// silently dropping them.
+		let verified = algorithm
+			.verify_detailed(
+				&BlockId::hash(metadata.best_hash),
+				&metadata.pre_hash,
+				metadata.pre_runtime.as_deref(),
+				&seal.0,
+				metadata.difficulty,
+			)
+			.map_err(|e| {
+				error(Error::VerificationFailed, "Unable to verify seal.", Some(e.to_string()))
+			})?;
+		if let Err(reason) = verified {
+			return Err(error(Error::InvalidSeal, "Seal rejected.", Some(reason.to_string())))
+		}
+
+		Ok(worker.submit(seal.0).await)
+	}
+}
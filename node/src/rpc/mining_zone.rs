@@ -0,0 +1,297 @@
+//! RPC methods exposing the mining zone, so that operators and miners can see where the
+//! blocks built on top of a given block may be mined.
+
+use jsonrpsee::{
+	core::{Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sha3pow::{geo, GeoLocator, GeoMiningApi, GeoPoint, MiningZoneParams, ZoneBlock};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{marker::PhantomData, sync::Arc};
+
+/// Largest raster the node computes for `miningZone_map`, in cells. This is the size of
+/// the raster at a resolution of 10 cells per degree.
+const MAX_RASTER_CELLS: u64 = 360 * 180 * 100;
+
+/// Largest raster computed for callers of the safe RPC interface, the size of the raster at
+/// a resolution of 2 cells per degree. Every cell costs a noise sample per octave, so
+/// larger rasters are only served over the unsafe interface, to operators.
+const MAX_SAFE_RASTER_CELLS: u64 = 360 * 180 * 4;
+
+/// Encodings of the zone raster.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ZoneMapFormat {
+	/// Run-length encoded bitmap, see [`ZoneRaster::Bitmap`].
+	Bitmap,
+	/// GeoJSON geometry, see [`ZoneRaster::GeoJson`].
+	GeoJson,
+}
+
+/// The zone raster in one of the [`ZoneMapFormat`]s.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "camelCase")]
+pub enum ZoneRaster {
+	/// Lengths of alternating runs of cells outside and inside the zone, in row-major
+	/// order starting at the south-west corner. The first run counts cells outside the
+	/// zone and is zero if the first cell is inside.
+	Bitmap {
+		/// The run lengths.
+		runs: Vec<u32>,
+	},
+	/// A `MultiPolygon` of rectangles covering the zone, in `[longitude, latitude]`
+	/// degrees.
+	GeoJson {
+		/// The GeoJSON geometry object.
+		geometry: serde_json::Value,
+	},
+}
+
+/// The mining zone for blocks built on top of a given block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneMap<Hash> {
+	/// The parent block the zone applies to.
+	pub at: Hash,
+	/// The zone parameters in effect at `at`.
+	pub params: MiningZoneParams,
+	/// Number of raster columns, from west to east.
+	pub columns: u32,
+	/// Number of raster rows, from south to north.
+	pub rows: u32,
+	/// Fraction of the earth's surface inside the zone.
+	pub coverage: f64,
+	/// The raster itself.
+	#[serde(flatten)]
+	pub raster: ZoneRaster,
+}
+
+/// A location to test against the zone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ZoneQuery {
+	/// A point in degrees.
+	Point {
+		/// Latitude, positive to the north.
+		lat: f64,
+		/// Longitude, positive to the east.
+		lon: f64,
+	},
+	/// An IP address, located with the node's GeoIP database.
+	Ip {
+		/// Textual IPv4 or IPv6 address.
+		ip: String,
+	},
+}
+
+/// Whether a location is inside the zone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneMembership<Hash> {
+	/// The parent block the zone applies to.
+	pub at: Hash,
+	/// Latitude of the location, rounded to whole microdegrees as the zone is evaluated.
+	pub lat: f64,
+	/// Longitude of the location, rounded to whole microdegrees.
+	pub lon: f64,
+	/// The raster cell containing the location, as `[column, row]`.
+	pub cell: (u32, u32),
+	/// Whether the location is inside the zone. Runtimes that decide eligibility themselves
+	/// answer this for the location, not the cell.
+	pub inside: bool,
+}
+
+/// Mining zone RPC methods.
+#[rpc(server)]
+pub trait MiningZoneApi<BlockHash> {
+	/// The zone that blocks built on top of `at`, or the best block, must be mined in.
+	/// Rasters finer than 2 cells per degree are only returned over the unsafe interface.
+	///
+	/// The raster is computed by the node from the zone parameters, like blocks of runtimes
+	/// without `GeoMiningApi::is_eligible` are verified. Runtimes deciding eligibility
+	/// themselves may disagree with it, ask `miningZone_contains` for a binding answer.
+	#[method(name = "miningZone_map")]
+	fn map(
+		&self,
+		format: Option<ZoneMapFormat>,
+		at: Option<BlockHash>,
+	) -> RpcResult<ZoneMap<BlockHash>>;
+
+	/// Whether a point or IP address is inside the zone for blocks built on top of `at`, or
+	/// the best block. Answered by the runtime where it decides eligibility itself.
+	#[method(name = "miningZone_contains")]
+	fn contains(
+		&self,
+		query: ZoneQuery,
+		at: Option<BlockHash>,
+	) -> RpcResult<ZoneMembership<BlockHash>>;
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The zone parameters or the block the zone derives from could not be read.
+	RuntimeError,
+	/// The queried location is invalid or cannot be located.
+	InvalidLocation,
+	/// The raster is too large to compute.
+	RasterTooLarge,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::InvalidLocation => 2,
+			Error::RasterTooLarge => 3,
+		}
+	}
+}
+
+fn error(code: Error, message: &str, data: Option<String>) -> JsonRpseeError {
+	CallError::Custom(ErrorObject::owned(code.into(), message, data)).into()
+}
+
+/// Implements [`MiningZoneApiServer`] on top of the client.
+pub struct MiningZone<C, Block> {
+	client: Arc<C>,
+	locator: Arc<dyn GeoLocator>,
+	deny_unsafe: DenyUnsafe,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> MiningZone<C, Block> {
+	/// Create a new instance answering IP queries with `locator`.
+	pub fn new(client: Arc<C>, locator: Arc<dyn GeoLocator>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { client, locator, deny_unsafe, _marker: Default::default() }
+	}
+}
+
+impl<C, Block> MiningZone<C, Block>
+where
+	Block: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: GeoMiningApi<Block>,
+{
+	/// Resolve `at`, read the zone parameters in effect there and describe the block the
+	/// zone is derived for.
+	fn zone(
+		&self,
+		at: Option<Block::Hash>,
+	) -> RpcResult<(Block::Hash, MiningZoneParams, ZoneBlock)> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let params = self.client.runtime_api().zone_params(&BlockId::hash(at)).map_err(|e| {
+			error(
+				Error::RuntimeError,
+				"Unable to query mining zone parameters.",
+				Some(e.to_string()),
+			)
+		})?;
+		let block = sha3pow::zone_block(self.client.as_ref(), &BlockId::hash(at)).map_err(|e| {
+			error(Error::RuntimeError, "Unable to find the parent block.", Some(e.to_string()))
+		})?;
+		Ok((at, params, block))
+	}
+}
+
+impl<C, Block> MiningZoneApiServer<Block::Hash> for MiningZone<C, Block>
+where
+	Block: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: GeoMiningApi<Block>,
+{
+	fn map(
+		&self,
+		format: Option<ZoneMapFormat>,
+		at: Option<Block::Hash>,
+	) -> RpcResult<ZoneMap<Block::Hash>> {
+		let (at, params, block) = self.zone(at)?;
+		let (columns, rows) = geo::grid_size(&params);
+		let size = u64::from(columns) * u64::from(rows);
+		if size > MAX_RASTER_CELLS {
+			return Err(error(
+				Error::RasterTooLarge,
+				"Mining zone resolution is too high to return the whole raster.",
+				Some(format!("{}x{} cells", columns, rows)),
+			))
+		}
+		if size > MAX_SAFE_RASTER_CELLS {
+			self.deny_unsafe.check_if_safe()?;
+		}
+
+		let cells = geo::raster(&block, &params);
+		let raster = match format.unwrap_or(ZoneMapFormat::Bitmap) {
+			ZoneMapFormat::Bitmap => ZoneRaster::Bitmap { runs: geo::run_lengths(&cells) },
+			ZoneMapFormat::GeoJson => {
+				// Exterior rings run counter-clockwise, as RFC 7946 asks for.
+				let polygons: Vec<_> = geo::rectangles(&params, &cells)
+					.into_iter()
+					.map(|(south, west, north, east)| {
+						let (sw, se, ne, nw) =
+							([west, south], [east, south], [east, north], [west, north]);
+						vec![[sw, se, ne, nw, sw]]
+					})
+					.collect();
+				ZoneRaster::GeoJson {
+					geometry: serde_json::json!({
+						"type": "MultiPolygon",
+						"coordinates": polygons,
+					}),
+				}
+			},
+		};
+
+		Ok(ZoneMap { at, params, columns, rows, coverage: geo::coverage(&params, &cells), raster })
+	}
+
+	fn contains(
+		&self,
+		query: ZoneQuery,
+		at: Option<Block::Hash>,
+	) -> RpcResult<ZoneMembership<Block::Hash>> {
+		let location = match query {
+			ZoneQuery::Point { lat, lon } => {
+				if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+					return Err(error(
+						Error::InvalidLocation,
+						"Coordinates are out of range.",
+						Some(format!("({}, {})", lat, lon)),
+					))
+				}
+				GeoPoint::from_degrees(lat, lon)
+			},
+			ZoneQuery::Ip { ip } => self.locator.locate_str(&ip).map_err(|e| {
+				error(Error::InvalidLocation, "Unable to locate IP address.", Some(e.to_string()))
+			})?,
+		};
+
+		let (at, params, block) = self.zone(at)?;
+		let cell = geo::cell_of(&params, location);
+		let (lat, lon) = location.to_degrees();
+		let api = self.client.runtime_api();
+		let runtime_error = |e: sp_api::ApiError| {
+			error(
+				Error::RuntimeError,
+				"Unable to query mining zone eligibility.",
+				Some(e.to_string()),
+			)
+		};
+		// Same as block verification: the runtime decides once it can, the node derives the
+		// zone itself for older runtimes.
+		let runtime_decides = api
+			.has_api_with::<dyn GeoMiningApi<Block>, _>(&BlockId::hash(at), |version| version >= 2)
+			.map_err(runtime_error)?;
+		let inside = if runtime_decides {
+			api.is_eligible(&BlockId::hash(at), at, location).map_err(runtime_error)?
+		} else {
+			let noise = geo::ZoneNoise::for_block(&params, &block);
+			geo::cell_is_in_zone(&noise, &params, cell)
+		};
+		Ok(ZoneMembership { at, lat, lon, cell, inside })
+	}
+}
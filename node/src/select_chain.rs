@@ -0,0 +1,245 @@
+//! Fork choice by cumulative proof of work.
+
+use sc_client_api::backend::Backend;
+use sc_consensus_pow::PowAux;
+use sp_blockchain::{lowest_common_ancestor, Backend as _, HeaderBackend};
+use sp_consensus::{Error as ConsensusError, SelectChain};
+use sp_core::U256;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+};
+use std::{fmt, iter, marker::PhantomData, sync::Arc};
+
+/// `SelectChain` that follows the chain with the most cumulative work rather than the
+/// longest one. The work is the total difficulty `sc_consensus_pow` records for every block
+/// it imports. Only chains building on the last finalized block are considered, so GRANDPA
+/// finality always wins over work.
+pub struct HeaviestChain<B, Block> {
+	backend: Arc<B>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<B, Block> Clone for HeaviestChain<B, Block> {
+	fn clone(&self) -> Self {
+		Self { backend: self.backend.clone(), _phantom: PhantomData }
+	}
+}
+
+fn lookup_error(err: impl fmt::Display) -> ConsensusError {
+	ConsensusError::ChainLookup(err.to_string())
+}
+
+impl<B, Block> HeaviestChain<B, Block>
+where
+	B: Backend<Block>,
+	Block: BlockT,
+{
+	/// Select chains from the blocks in `backend`.
+	pub fn new(backend: Arc<B>) -> Self {
+		Self { backend, _phantom: PhantomData }
+	}
+
+	/// Total difficulty of the chain ending in `hash`. Zero for the genesis block.
+	fn total_difficulty(&self, hash: &Block::Hash) -> Result<U256, ConsensusError> {
+		PowAux::<U256>::read::<_, Block>(&*self.backend, hash)
+			.map(|aux| aux.total_difficulty)
+			.map_err(lookup_error)
+	}
+
+	/// Whether `descendant` is `ancestor` or builds on it.
+	fn builds_on(
+		&self,
+		descendant: Block::Hash,
+		ancestor: Block::Hash,
+	) -> Result<bool, ConsensusError> {
+		let common = lowest_common_ancestor(self.backend.blockchain(), descendant, ancestor)
+			.map_err(lookup_error)?;
+		Ok(common.hash == ancestor)
+	}
+
+	/// The heaviest leaf building on `base`. Ties go to the current best block, so that the
+	/// node does not switch back and forth between equally heavy forks.
+	fn heaviest_leaf(&self, base: Block::Hash) -> Result<Option<Block::Hash>, ConsensusError> {
+		let blockchain = self.backend.blockchain();
+		let best_hash = blockchain.info().best_hash;
+		let leaves = blockchain.leaves().map_err(lookup_error)?;
+
+		let mut candidates = iter::once(best_hash)
+			.chain(leaves.into_iter().filter(|hash| *hash != best_hash))
+			.map(|hash| Ok((self.total_difficulty(&hash)?, hash)))
+			.collect::<Result<Vec<_>, ConsensusError>>()?;
+		// Reading the work is cheap, walking the ancestry is not. Checking the heaviest
+		// candidates first, only leaves that would win are walked. The sort is stable, which
+		// keeps the best block ahead of leaves as heavy as it.
+		candidates.sort_by(|(work, _), (other, _)| other.cmp(work));
+		for (_, hash) in candidates {
+			if self.builds_on(hash, base)? {
+				return Ok(Some(hash))
+			}
+		}
+		Ok(None)
+	}
+
+	fn header(&self, hash: Block::Hash) -> Result<Block::Header, ConsensusError> {
+		self.backend
+			.blockchain()
+			.header(BlockId::Hash(hash))
+			.map_err(lookup_error)?
+			.ok_or_else(|| ConsensusError::ChainLookup(format!("Missing header for {:?}", hash)))
+	}
+}
+
+#[async_trait::async_trait]
+impl<B, Block> SelectChain<Block> for HeaviestChain<B, Block>
+where
+	B: Backend<Block>,
+	Block: BlockT,
+{
+	async fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError> {
+		self.backend.blockchain().leaves().map_err(lookup_error)
+	}
+
+	async fn best_chain(&self) -> Result<Block::Header, ConsensusError> {
+		let finalized = self.backend.blockchain().info().finalized_hash;
+		let best = self.heaviest_leaf(finalized)?.unwrap_or(finalized);
+		self.header(best)
+	}
+
+	async fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Block::Hash, ConsensusError> {
+		// Keep the leaves from changing while the target is picked.
+		let _import_lock = self.backend.get_import_lock().read();
+
+		let mut hash = match self.heaviest_leaf(target_hash)? {
+			Some(hash) => hash,
+			None => return Ok(target_hash),
+		};
+
+		// Vote for the ancestor at `maybe_max_number`, but never below the target.
+		if let Some(max_number) = maybe_max_number {
+			let max_number = max_number.max(*self.header(target_hash)?.number());
+			loop {
+				let header = self.header(hash)?;
+				if *header.number() <= max_number {
+					break
+				}
+				hash = *header.parent_hash();
+			}
+		}
+		Ok(hash)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_client_api::{backend::NewBlockState, in_mem, AuxStore};
+	use sc_consensus_pow::POW_AUX_PREFIX;
+	use sp_core::{Encode, H256};
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper, Header};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+	type TestChain = HeaviestChain<in_mem::Backend<Block>, Block>;
+
+	fn new_chain() -> (TestChain, H256) {
+		let chain = HeaviestChain::new(Arc::new(in_mem::Backend::new()));
+		let genesis = push(&chain, None, 0, 0, NewBlockState::Final);
+		(chain, genesis)
+	}
+
+	/// Import a block on top of `parent`, given with its number, with `difficulty` more work.
+	/// `fork` tells apart blocks with the same parent.
+	fn push(
+		chain: &TestChain,
+		parent: Option<(H256, u64)>,
+		fork: u64,
+		difficulty: u64,
+		state: NewBlockState,
+	) -> H256 {
+		let (parent_hash, number) =
+			parent.map_or((H256::zero(), 0), |(hash, number)| (hash, number + 1));
+		let header = Header::new(
+			number,
+			Default::default(),
+			H256::from_low_u64_be(fork),
+			parent_hash,
+			Default::default(),
+		);
+		let hash = header.hash();
+		let total_difficulty = match parent {
+			Some((parent_hash, _)) => chain.total_difficulty(&parent_hash).unwrap(),
+			None => U256::zero(),
+		} + difficulty;
+		let aux = PowAux { difficulty: U256::from(difficulty), total_difficulty };
+		let key = [&POW_AUX_PREFIX[..], hash.as_bytes()].concat();
+		chain.backend.insert_aux(&[(&key[..], &aux.encode()[..])], &[]).unwrap();
+		chain.backend.blockchain().insert(hash, header, None, None, state).unwrap();
+		hash
+	}
+
+	/// Extend `parent`, given with its number, by `count` best blocks of `difficulty` each.
+	fn extend(
+		chain: &TestChain,
+		mut parent: (H256, u64),
+		count: u64,
+		difficulty: u64,
+	) -> Vec<H256> {
+		(0..count)
+			.map(|_| {
+				let hash = push(chain, Some(parent), 0, difficulty, NewBlockState::Best);
+				parent = (hash, parent.1 + 1);
+				hash
+			})
+			.collect()
+	}
+
+	fn best(chain: &TestChain) -> H256 {
+		futures::executor::block_on(chain.best_chain()).unwrap().hash()
+	}
+
+	#[test]
+	fn heavier_short_fork_beats_a_longer_light_one() {
+		let (chain, genesis) = new_chain();
+		let long = extend(&chain, (genesis, 0), 3, 10);
+		let short = push(&chain, Some((genesis, 0)), 1, 50, NewBlockState::Normal);
+
+		assert_eq!(chain.backend.blockchain().info().best_hash, long[2]);
+		assert_eq!(best(&chain), short);
+	}
+
+	#[test]
+	fn equally_heavy_forks_keep_the_current_best() {
+		let (chain, genesis) = new_chain();
+		let current = push(&chain, Some((genesis, 0)), 0, 10, NewBlockState::Best);
+		push(&chain, Some((genesis, 0)), 1, 10, NewBlockState::Normal);
+
+		assert_eq!(best(&chain), current);
+	}
+
+	#[test]
+	fn leaves_not_building_on_the_finalized_block_are_ignored() {
+		let (chain, genesis) = new_chain();
+		let finalized = push(&chain, Some((genesis, 0)), 0, 10, NewBlockState::Final);
+		push(&chain, Some((genesis, 0)), 1, 100, NewBlockState::Normal);
+
+		assert_eq!(best(&chain), finalized);
+	}
+
+	#[test]
+	fn finality_target_respects_maybe_max_number() {
+		let (chain, genesis) = new_chain();
+		let blocks = extend(&chain, (genesis, 0), 5, 10);
+		let target = |hash, max_number| {
+			futures::executor::block_on(chain.finality_target(hash, max_number)).unwrap()
+		};
+
+		assert_eq!(target(genesis, None), blocks[4]);
+		assert_eq!(target(genesis, Some(3)), blocks[2]);
+		// Never below the target itself.
+		assert_eq!(target(blocks[1], Some(0)), blocks[1]);
+	}
+}
@@ -1,4 +1,4 @@
-use node_template_runtime::{self, opaque::Block, RuntimeApi};
+use node_template_runtime::{self, opaque::Block, AccountId, RuntimeApi};
 use sc_client_api::{BlockBackend, ExecutorProvider, HeaderBackend};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_finality_grandpa::SharedVoterState;
@@ -6,9 +6,14 @@ use sc_keystore::LocalKeystore;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use std::{sync::Arc, time::Duration};
+use sp_consensus::SyncOracle;
 use sp_inherents::CreateInherentDataProviders;
 use sp_core::{Encode, U256};
-use minipow::MiniPow;  // ← our new toy PoW
+use sha3pow::{Attestation, GeoPoint, LocationClaim, PreDigest};
+use crate::metrics::{MeteredPow, PowMetrics};
+use crate::pow::{NodePowAlgorithm, PowParams};
+use crate::rpc::mining::{LocalMiner, MiningWorker};
+use crate::select_chain::HeaviestChain;
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -33,10 +38,11 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 pub(crate) type FullClient =
     sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
 type FullBackend = sc_service::TFullBackend<Block>;
-type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
+type FullSelectChain = HeaviestChain<FullBackend, Block>;
 
 pub fn new_partial(
     config: &Configuration,
+    pow: &PowParams,
 ) -> Result<
     sc_service::PartialComponents<
         FullClient,
@@ -52,10 +58,12 @@ pub fn new_partial(
                 sc_finality_grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
                 FullClient,
                 FullSelectChain,
-                MiniPow,
+                MeteredPow,
                 impl sp_consensus::CanAuthorWith<Block>,
                 impl CreateInherentDataProviders<Block, ()>,
             >,
+            NodePowAlgorithm,
+            Option<PowMetrics>,
         ),
     >,
     ServiceError,
@@ -96,7 +104,8 @@ pub fn new_partial(
         telemetry
     });
 
-    let select_chain = sc_consensus::LongestChain::new(backend.clone());
+    // Follow the chain with the most work, for block import and mining alike.
+    let select_chain = HeaviestChain::new(backend.clone());
 
     let transaction_pool = sc_transaction_pool::BasicPool::new_full(
         config.transaction_pool.clone(),
@@ -116,14 +125,22 @@ pub fn new_partial(
 
     let can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
 
-    // Instantiate our MiniPow algorithm once
-    let pow_algo = MiniPow;
+    // Instantiate the selected PoW algorithm once
+    let pow_algo = pow.build(&*config.chain_spec, client.clone()).map_err(ServiceError::Other)?;
 
-    // PoW block import using MiniPow
+    // Count the seals block import rejects, by reason
+    let pow_metrics = config.prometheus_registry().and_then(|registry| {
+        PowMetrics::register(registry)
+            .map_err(|err| log::warn!("Failed to register PoW metrics: {}", err))
+            .ok()
+    });
+    let metered_pow = MeteredPow::new(pow_algo.clone(), pow_metrics.clone());
+
+    // PoW block import using the selected algorithm
     let pow_block_import = sc_consensus_pow::PowBlockImport::new(
         grandpa_block_import,
         client.clone(),
-        pow_algo.clone(),               // ← MiniPow here
+        metered_pow.clone(),
         0,                              // check inherents starting at block 0
         select_chain.clone(),
         move |_, ()| async move {
@@ -133,11 +150,11 @@ pub fn new_partial(
         can_author_with.clone(),
     );
 
-    // Import queue with MiniPow
+    // Import queue with the selected algorithm
     let import_queue = sc_consensus_pow::import_queue(
         Box::new(pow_block_import.clone()),
         None,                           // no justification import
-        pow_algo.clone(),               // ← MiniPow here
+        metered_pow,
         &task_manager.spawn_essential_handle(),
         config.prometheus_registry(),
     )?;
@@ -150,16 +167,68 @@ pub fn new_partial(
         keystore_container,
         select_chain,
         transaction_pool,
-        other: (grandpa_link, telemetry, pow_block_import),
+        other: (grandpa_link, telemetry, pow_block_import, pow_algo, pow_metrics),
     })
 }
 
+/// Options for the local mining worker.
+#[derive(Debug, Clone)]
+pub struct MiningConfig {
+    /// Whether this node builds and mines blocks at all.
+    pub enabled: bool,
+    /// Account credited as the author of locally mined blocks.
+    pub author: Option<AccountId>,
+    /// IP address claimed as this node's location in mined blocks.
+    pub ip: String,
+    /// Witness attestations backing the location claim.
+    pub attestations: Vec<Attestation>,
+    /// Number of CPU miner threads.
+    pub threads: usize,
+    /// Stratum pool server to run next to the mining worker.
+    #[cfg(feature = "stratum")]
+    pub stratum: Option<crate::stratum::StratumConfig>,
+}
+
+impl MiningConfig {
+    /// The pre-runtime digest the mining worker puts into every block it builds, claiming
+    /// to be at `point`.
+    fn pre_digest(&self, point: Option<GeoPoint>) -> PreDigest {
+        PreDigest {
+            author: self.author.clone(),
+            location: LocationClaim::new(&self.ip, point),
+            attestations: self.attestations.clone(),
+        }
+    }
+}
+
+/// Sync oracle of the mining worker. The worker does not build blocks while the node is
+/// syncing, unless `--force-authoring` is given.
+#[derive(Clone)]
+struct MiningSyncOracle<SO> {
+    inner: SO,
+    force_authoring: bool,
+}
+
+impl<SO: SyncOracle> SyncOracle for MiningSyncOracle<SO> {
+    fn is_major_syncing(&mut self) -> bool {
+        !self.force_authoring && self.inner.is_major_syncing()
+    }
+
+    fn is_offline(&mut self) -> bool {
+        !self.force_authoring && self.inner.is_offline()
+    }
+}
+
 fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
     Err("Remote Keystore not supported.")
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+    mut config: Configuration,
+    pow: PowParams,
+    mining: MiningConfig,
+) -> Result<TaskManager, ServiceError> {
     let sc_service::PartialComponents {
         client,
         backend,
@@ -168,8 +237,8 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
         mut keystore_container,
         select_chain,
         transaction_pool,
-        other: (grandpa_link, mut telemetry, pow_block_import),
-    } = new_partial(&config)?;
+        other: (grandpa_link, mut telemetry, pow_block_import, pow_algo, pow_metrics),
+    } = new_partial(&config, &pow)?;
 
     if let Some(url) = &config.keystore_remote {
         match remote_keystore(url) {
@@ -220,17 +289,127 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
     }
 
     let role = config.role.clone();
-    let _force_authoring = config.force_authoring;
+    let force_authoring = config.force_authoring;
     let name = config.network.node_name.clone();
     let enable_grandpa = !config.disable_grandpa;
     let prometheus_registry = config.prometheus_registry().cloned();
 
+    // Public RPC and plain full nodes must never propose blocks.
+    if mining.enabled && !role.is_authority() {
+        return Err(ServiceError::Other("Mining requires the node to run with --validator".into()))
+    }
+
+    // Answer mining zone queries by IP with the locator blocks are verified with.
+    let geo_locator = match pow_algo.locator() {
+        Some(locator) => locator,
+        None => pow.locator().map_err(ServiceError::Other)?,
+    };
+
+    let (miner, miner_stats) = if mining.enabled {
+        // Create proposer and authoring gate
+        let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+            task_manager.spawn_handle(),
+            client.clone(),
+            transaction_pool.clone(),
+            prometheus_registry.as_ref(),
+            telemetry.as_ref().map(|x| x.handle()),
+        );
+
+        let can_author_with =
+            sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
+
+        // Locally mined blocks record their author on chain through the block author inherent.
+        let author = mining.author.clone();
+
+        // The claim is fixed for the lifetime of the worker. It names a point rather than a
+        // quota region, the runtime looks up the region in every block, so it stays valid
+        // when governance redraws the quota regions. Nodes that cannot be located claim no
+        // point, their blocks fail the mining zone check anyway.
+        let point = geo_locator.locate_str(&mining.ip).ok();
+        if let Some(point) = point {
+            log::info!("⛏  Claiming location {:?}", point);
+        }
+
+        // Start the mining worker with the selected algorithm
+        let (worker, worker_task) = sc_consensus_pow::start_mining_worker(
+            Box::new(pow_block_import),
+            client.clone(),
+            select_chain.clone(),
+            pow_algo.clone(),
+            proposer_factory,
+            MiningSyncOracle { inner: network.clone(), force_authoring },
+            network.clone(),
+            Some(mining.pre_digest(point).encode()),
+            move |_, ()| {
+                let author = author.clone();
+                async move {
+                    let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+                    let author = pallet_block_author::InherentDataProvider(author);
+                    Ok((timestamp, author))
+                }
+            },
+            Duration::from_secs(2),
+            Duration::from_secs(2),
+            can_author_with,
+        );
+
+        task_manager
+            .spawn_essential_handle()
+            .spawn_blocking("pow", Some("block-authoring"), worker_task);
+
+        // External miners fetch work from the same worker, through the mining RPC or the
+        // Stratum pool server.
+        let shared_worker: Arc<dyn MiningWorker> = Arc::new(worker.clone());
+
+        #[cfg(feature = "stratum")]
+        if let Some(stratum) = mining.stratum.clone() {
+            task_manager.spawn_handle().spawn(
+                "stratum",
+                Some("block-authoring"),
+                crate::stratum::run(
+                    stratum,
+                    shared_worker.clone(),
+                    pow_algo.clone(),
+                    task_manager.spawn_handle(),
+                ),
+            );
+        }
+
+        let stats = (mining.threads > 0)
+            .then(|| crate::miner::start(worker, pow_algo.clone(), mining.threads));
+        let miner = LocalMiner::new(
+            mining.author.clone(),
+            shared_worker,
+            pow_algo.clone(),
+            mining.threads,
+            stats.clone(),
+        );
+        (miner, stats)
+    } else {
+        log::info!("⛏  Not mining, start with --mine to build blocks");
+        (LocalMiner::disabled(), None)
+    };
+
+    if let Some(metrics) = pow_metrics {
+        task_manager.spawn_handle().spawn(
+            "pow-metrics",
+            None,
+            crate::metrics::run(metrics, client.clone(), pow_algo, miner_stats),
+        );
+    }
+
     let rpc_extensions_builder = {
         let client = client.clone();
         let pool = transaction_pool.clone();
 
         Box::new(move |deny_unsafe, _| {
-            let deps = crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+            let deps = crate::rpc::FullDeps {
+                client: client.clone(),
+                pool: pool.clone(),
+                deny_unsafe,
+                geo_locator: geo_locator.clone(),
+                miner: miner.clone(),
+            };
             crate::rpc::create_full(deps).map_err(Into::into)
         })
     };
@@ -240,7 +419,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
         client: client.clone(),
         keystore: keystore_container.sync_keystore(),
         task_manager: &mut task_manager,
-        transaction_pool: transaction_pool.clone(),
+        transaction_pool,
         rpc_builder: rpc_extensions_builder,
         backend,
         system_rpc_tx,
@@ -248,40 +427,6 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
         telemetry: telemetry.as_mut(),
     })?;
 
-    // Create proposer and authoring gate
-    let proposer_factory = sc_basic_authorship::ProposerFactory::new(
-        task_manager.spawn_handle(),
-        client.clone(),
-        transaction_pool,
-        prometheus_registry.as_ref(),
-        telemetry.as_ref().map(|x| x.handle()),
-    );
-
-    let can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
-
-    // Start the mining worker with MiniPow (unconditionally)
-    let (_worker, worker_task) = sc_consensus_pow::start_mining_worker(
-        Box::new(pow_block_import),
-        client.clone(),
-        select_chain.clone(),
-        MiniPow,
-        proposer_factory,
-        network.clone(),
-        network.clone(),
-        None,
-        move |_, ()| async move {
-            let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
-            Ok(timestamp)
-        },
-        Duration::from_secs(2),
-        Duration::from_secs(2),
-        can_author_with,
-    );
-
-    task_manager
-        .spawn_essential_handle()
-        .spawn_blocking("pow", Some("block-authoring"), worker_task);
-
     // Set up GRANDPA finality
     let keystore = if role.is_authority() { Some(keystore_container.sync_keystore()) } else { None };
     let grandpa_config = sc_finality_grandpa::Config {
@@ -0,0 +1,600 @@
+//! Stratum-like pool server sharing the mining worker's templates among many miners.
+//!
+//! Miners connect over TCP and exchange newline-delimited JSON-RPC messages:
+//!
+//! - `mining.subscribe` and `mining.authorize [worker, password]` register a worker. The
+//!   password is ignored, the worker name only keys the share accounting.
+//! - The server answers with `mining.set_difficulty [share_difficulty]` and sends
+//!   `mining.notify [job_id, pre_hash, algorithm, difficulty, clean_jobs]` for every new
+//!   template.
+//! - `mining.submit [worker, job_id, nonce]` hands in a share. Shares meeting the block
+//!   difficulty are sealed and submitted to the mining worker.
+
+use crate::{
+	pow::{NodePowAlgorithm, Share},
+	rpc::mining::MiningWorker,
+};
+use sc_service::SpawnTaskHandle;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sp_core::{H256, U256};
+use std::{
+	collections::{HashMap, HashSet},
+	io,
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+	sync::{watch, Semaphore},
+};
+
+/// How often the mining worker is polled for a new template.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the share counts are logged.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most workers one connection can authorize.
+const MAX_WORKERS_PER_CONNECTION: usize = 16;
+
+/// Most workers authorized on all connections together.
+const MAX_WORKERS: usize = 4_096;
+
+/// Most shares remembered per job to tell duplicates. Once reached, the job only takes
+/// shares sealing a block, which the import queue rejects duplicates of anyway.
+const MAX_SHARES_PER_JOB: usize = 65_536;
+
+/// Longest request line a miner may send, in bytes without the newline. Connections
+/// sending longer lines are dropped.
+const MAX_LINE_LENGTH: usize = 4_096;
+
+/// Most miner connections served at the same time. Further connections are closed right
+/// away.
+const MAX_CONNECTIONS: usize = 1_024;
+
+/// Options of the pool server.
+#[derive(Debug, Clone)]
+pub struct StratumConfig {
+	/// Address the server listens on.
+	pub listen: SocketAddr,
+	/// Difficulty a share has to meet. Should be well below the block difficulty, so that
+	/// every miner hands in shares regularly.
+	pub share_difficulty: U256,
+}
+
+/// Reasons for rejecting a request, by Stratum error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorCode {
+	Other,
+	JobNotFound,
+	DuplicateShare,
+	LowDifficultyShare,
+	UnauthorizedWorker,
+	/// The job took as many shares as the pool remembers to tell duplicates. Stratum has no
+	/// code of its own for this.
+	TooManyShares,
+}
+
+impl ErrorCode {
+	fn code(self) -> i32 {
+		match self {
+			Self::Other | Self::TooManyShares => 20,
+			Self::JobNotFound => 21,
+			Self::DuplicateShare => 22,
+			Self::LowDifficultyShare => 23,
+			Self::UnauthorizedWorker => 24,
+		}
+	}
+
+	fn error(self, message: &str) -> Value {
+		json!([self.code(), message, null])
+	}
+
+	/// Message for a share rejected with this code.
+	fn rejection(self) -> &'static str {
+		match self {
+			Self::Other => "Block rejected",
+			Self::JobNotFound => "Job not found",
+			Self::DuplicateShare => "Duplicate share",
+			Self::LowDifficultyShare => "Low difficulty share",
+			Self::UnauthorizedWorker => "Unauthorized worker",
+			Self::TooManyShares => "Too many shares for this job, wait for the next one",
+		}
+	}
+}
+
+/// A template handed out to the miners.
+#[derive(Debug, Clone)]
+struct Job {
+	id: u64,
+	pre_hash: H256,
+	difficulty: U256,
+}
+
+/// Shares handed in by one worker.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerShares {
+	/// Shares meeting the share difficulty.
+	pub accepted: u64,
+	/// Shares that were stale, duplicated or too weak.
+	pub rejected: u64,
+	/// Shares that sealed a block.
+	pub blocks: u64,
+}
+
+/// A worker authorized on at least one open connection.
+#[derive(Default)]
+struct Worker {
+	/// Number of open connections the worker is authorized on.
+	connections: usize,
+	shares: WorkerShares,
+}
+
+#[derive(Default)]
+struct PoolState {
+	/// Job the nonces in `nonces` were submitted for.
+	job_id: u64,
+	/// Nonces of the shares accepted for the current job, to reject duplicates. Holds at most
+	/// `MAX_SHARES_PER_JOB` nonces.
+	nonces: HashSet<U256>,
+	/// Workers authorized on open connections. Their shares are forgotten once they
+	/// disconnected from every connection.
+	workers: HashMap<String, Worker>,
+}
+
+struct Pool {
+	worker: Arc<dyn MiningWorker>,
+	algorithm: NodePowAlgorithm,
+	share_difficulty: U256,
+	state: Mutex<PoolState>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+	id: Value,
+	method: String,
+	#[serde(default)]
+	params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+	id: Value,
+	result: Value,
+	error: Value,
+}
+
+impl Pool {
+	fn state(&self) -> std::sync::MutexGuard<PoolState> {
+		self.state.lock().expect("Pool state is never held across a panic; qed")
+	}
+
+	/// Authorize `worker` on a connection that authorized the `authorized` workers so far.
+	fn authorize(
+		&self,
+		worker: &str,
+		authorized: &mut HashSet<String>,
+	) -> Result<(), &'static str> {
+		if authorized.contains(worker) {
+			return Ok(())
+		}
+		if authorized.len() >= MAX_WORKERS_PER_CONNECTION {
+			return Err("Too many workers on this connection")
+		}
+		let mut state = self.state();
+		if !state.workers.contains_key(worker) && state.workers.len() >= MAX_WORKERS {
+			return Err("Too many workers")
+		}
+		state.workers.entry(worker.to_owned()).or_default().connections += 1;
+		authorized.insert(worker.to_owned());
+		Ok(())
+	}
+
+	/// Release the workers a closed connection authorized.
+	fn disconnect(&self, authorized: &HashSet<String>) {
+		let mut state = self.state();
+		for name in authorized {
+			if let Some(worker) = state.workers.get_mut(name) {
+				worker.connections -= 1;
+				if worker.connections == 0 {
+					state.workers.remove(name);
+				}
+			}
+		}
+	}
+
+	/// Account a share of the authorized `worker`, graded as `result`.
+	fn record(&self, worker: &str, result: Result<bool, ErrorCode>) {
+		let mut state = self.state();
+		let shares = match state.workers.get_mut(worker) {
+			Some(worker) => &mut worker.shares,
+			None => return,
+		};
+		match result {
+			Ok(block) => {
+				shares.accepted += 1;
+				shares.blocks += block as u64;
+			},
+			Err(_) => shares.rejected += 1,
+		}
+	}
+
+	/// Grade a share for `job` and submit it if it seals a block. Resolves to whether the
+	/// share sealed a block.
+	async fn submit(&self, job: &Job, job_id: &str, nonce: U256) -> Result<bool, ErrorCode> {
+		if job_id != format!("{:x}", job.id) {
+			return Err(ErrorCode::JobNotFound)
+		}
+
+		// Only shares meeting the share difficulty are remembered, so that flooding the pool
+		// with junk nonces does not grow the duplicate check.
+		let share =
+			self.algorithm.grade(&job.pre_hash, job.difficulty, self.share_difficulty, nonce);
+		let seal = match share {
+			Share::Invalid => return Err(ErrorCode::LowDifficultyShare),
+			Share::Valid => None,
+			Share::Block(seal) => Some(seal),
+		};
+		{
+			let mut state = self.state();
+			if state.job_id != job.id {
+				state.job_id = job.id;
+				state.nonces.clear();
+			}
+			if state.nonces.contains(&nonce) {
+				return Err(ErrorCode::DuplicateShare)
+			}
+			if state.nonces.len() < MAX_SHARES_PER_JOB {
+				state.nonces.insert(nonce);
+			} else if seal.is_none() {
+				return Err(ErrorCode::TooManyShares)
+			}
+		}
+
+		let seal = match seal {
+			Some(seal) => seal,
+			None => return Ok(false),
+		};
+		// The worker seals whatever template it currently builds.
+		let current = self.worker.metadata().map(|metadata| metadata.pre_hash);
+		if current != Some(job.pre_hash) {
+			return Err(ErrorCode::JobNotFound)
+		}
+		if self.worker.submit(seal).await {
+			log::info!("⛏  Pool found block with pre-hash {:?}", job.pre_hash);
+			Ok(true)
+		} else {
+			Err(ErrorCode::Other)
+		}
+	}
+}
+
+fn notify(method: &str, params: Value) -> Value {
+	json!({ "id": null, "method": method, "params": params })
+}
+
+fn job_notification(job: &Job, algorithm: &NodePowAlgorithm) -> Value {
+	notify(
+		"mining.notify",
+		json!([
+			format!("{:x}", job.id),
+			job.pre_hash,
+			algorithm.seal_kind().as_str(),
+			job.difficulty,
+			true
+		]),
+	)
+}
+
+async fn send(
+	stream: &mut (impl AsyncWriteExt + Unpin),
+	message: &impl Serialize,
+) -> io::Result<()> {
+	let mut line = serde_json::to_vec(message)?;
+	line.push(b'\n');
+	stream.write_all(&line).await
+}
+
+/// Answer a request of a miner whose connection authorized the `authorized` workers.
+async fn handle(
+	pool: &Pool,
+	request: Request,
+	job: Option<Job>,
+	authorized: &mut HashSet<String>,
+) -> Result<Value, Value> {
+	match request.method.as_str() {
+		"mining.subscribe" => Ok(json!([null, null])),
+		"mining.authorize" => match serde_json::from_value::<Vec<String>>(request.params) {
+			Ok(params) if !params.is_empty() => {
+				pool.authorize(&params[0], authorized)
+					.map_err(|message| ErrorCode::UnauthorizedWorker.error(message))?;
+				Ok(json!(true))
+			},
+			_ => Err(ErrorCode::Other.error("Expected [worker, password]")),
+		},
+		"mining.submit" => {
+			let (worker, job_id, nonce) =
+				serde_json::from_value::<(String, String, U256)>(request.params)
+					.map_err(|_| ErrorCode::Other.error("Expected [worker, job_id, nonce]"))?;
+			if !authorized.contains(&worker) {
+				return Err(ErrorCode::UnauthorizedWorker.error("Unauthorized worker"))
+			}
+			let result = match &job {
+				Some(job) => pool.submit(job, &job_id, nonce).await,
+				None => Err(ErrorCode::JobNotFound),
+			};
+			pool.record(&worker, result);
+			result.map(|_| json!(true)).map_err(|code| code.error(code.rejection()))
+		},
+		_ => Err(ErrorCode::Other.error("Unknown method")),
+	}
+}
+
+/// Read up to the next newline into `line`, which keeps the bytes read so far. Cancel safe,
+/// as partially read lines stay in `line`. Resolves to `false` once the stream ended and
+/// fails on lines longer than `MAX_LINE_LENGTH`, so that a miner that never ends its line
+/// cannot grow the buffer without bound.
+async fn read_line<R: AsyncBufRead + Unpin>(
+	reader: &mut R,
+	line: &mut Vec<u8>,
+) -> io::Result<bool> {
+	loop {
+		// One byte more than the longest line, for its newline.
+		let limit = (MAX_LINE_LENGTH + 1).saturating_sub(line.len()) as u64;
+		let read = (&mut *reader).take(limit).read_until(b'\n', line).await?;
+		if line.ends_with(b"\n") {
+			return Ok(true)
+		}
+		if line.len() > MAX_LINE_LENGTH {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "Request line too long"))
+		}
+		if read == 0 {
+			return Ok(false)
+		}
+	}
+}
+
+/// Serve one miner connection until it closes. The workers it authorizes are added to
+/// `authorized`.
+async fn serve(
+	pool: &Pool,
+	stream: TcpStream,
+	mut jobs: watch::Receiver<Option<Job>>,
+	authorized: &mut HashSet<String>,
+) -> io::Result<()> {
+	let (read, mut write) = stream.into_split();
+	let mut reader = BufReader::new(read);
+	let mut line = Vec::new();
+
+	loop {
+		tokio::select! {
+			read = read_line(&mut reader, &mut line) => {
+				if !read? {
+					return Ok(())
+				}
+				let request = serde_json::from_slice::<Request>(&line);
+				line.clear();
+				let request = match request {
+					Ok(request) => request,
+					Err(err) => {
+						log::debug!(target: "stratum", "Malformed request: {}", err);
+						continue
+					},
+				};
+
+				let (id, method) = (request.id.clone(), request.method.clone());
+				let job = jobs.borrow().clone();
+				let handled = handle(pool, request, job.clone(), authorized).await;
+				let (result, error) = match handled {
+					Ok(result) => (result, Value::Null),
+					Err(error) => (Value::Null, error),
+				};
+				let authorized_now = method == "mining.authorize" && error.is_null();
+				send(&mut write, &Response { id, result, error }).await?;
+
+				if authorized_now {
+					let difficulty = json!([pool.share_difficulty]);
+					send(&mut write, &notify("mining.set_difficulty", difficulty)).await?;
+					if let Some(job) = &job {
+						send(&mut write, &job_notification(job, &pool.algorithm)).await?;
+					}
+				}
+			},
+			changed = jobs.changed() => {
+				if changed.is_err() {
+					return Ok(())
+				}
+				let job = jobs.borrow().clone();
+				if let (Some(job), false) = (job, authorized.is_empty()) {
+					send(&mut write, &job_notification(&job, &pool.algorithm)).await?;
+				}
+			},
+		}
+	}
+}
+
+/// Poll the mining worker and publish every new template as a job.
+async fn publish_jobs(worker: Arc<dyn MiningWorker>, jobs: watch::Sender<Option<Job>>) {
+	let mut next_id = 0;
+	let mut current = None;
+	let mut interval = tokio::time::interval(POLL_INTERVAL);
+	loop {
+		interval.tick().await;
+		let metadata = worker.metadata();
+		if metadata.as_ref().map(|metadata| metadata.pre_hash) == current {
+			continue
+		}
+		current = metadata.as_ref().map(|metadata| metadata.pre_hash);
+		let job = metadata.map(|metadata| {
+			next_id += 1;
+			Job { id: next_id, pre_hash: metadata.pre_hash, difficulty: metadata.difficulty }
+		});
+		if jobs.send(job).is_err() {
+			return
+		}
+	}
+}
+
+/// Log the shares of every worker, forever.
+async fn report(pool: Arc<Pool>) {
+	let mut interval = tokio::time::interval(REPORT_INTERVAL);
+	loop {
+		interval.tick().await;
+		for (worker, Worker { shares, .. }) in &pool.state().workers {
+			log::info!(
+				target: "stratum",
+				"{}: {} share(s) accepted, {} rejected, {} block(s)",
+				worker,
+				shares.accepted,
+				shares.rejected,
+				shares.blocks,
+			);
+		}
+	}
+}
+
+/// Run the pool server on the templates of `worker` until the node shuts down. Its tasks
+/// are spawned with `spawner`.
+pub async fn run(
+	config: StratumConfig,
+	worker: Arc<dyn MiningWorker>,
+	algorithm: NodePowAlgorithm,
+	spawner: SpawnTaskHandle,
+) {
+	let listener = match TcpListener::bind(config.listen).await {
+		Ok(listener) => listener,
+		Err(err) => {
+			log::error!("Unable to start the Stratum server on {}: {}", config.listen, err);
+			return
+		},
+	};
+	log::info!("⛏  Stratum server listening on {}", config.listen);
+
+	let pool = Arc::new(Pool {
+		worker: worker.clone(),
+		algorithm,
+		share_difficulty: config.share_difficulty,
+		state: Default::default(),
+	});
+	let (jobs_tx, jobs) = watch::channel(None);
+	spawner.spawn("stratum-jobs", Some("block-authoring"), publish_jobs(worker, jobs_tx));
+	spawner.spawn("stratum-report", Some("block-authoring"), report(pool.clone()));
+
+	let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+	loop {
+		match listener.accept().await {
+			Ok((stream, peer)) => {
+				// Dropping the stream closes the connection.
+				let permit = match connections.clone().try_acquire_owned() {
+					Ok(permit) => permit,
+					Err(_) => {
+						log::debug!(target: "stratum", "Too many miners, closing {}", peer);
+						continue
+					},
+				};
+				log::debug!(target: "stratum", "Miner connected from {}", peer);
+				let (pool, jobs) = (pool.clone(), jobs.clone());
+				spawner.spawn("stratum-miner", Some("block-authoring"), async move {
+					let _permit = permit;
+					let mut authorized = HashSet::new();
+					let served = serve(&pool, stream, jobs, &mut authorized).await;
+					pool.disconnect(&authorized);
+					if let Err(err) = served {
+						log::debug!(target: "stratum", "Miner {} disconnected: {}", peer, err);
+					}
+				});
+			},
+			Err(err) => log::warn!("Unable to accept Stratum connection: {}", err),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, future::BoxFuture, FutureExt};
+	use minipow::MiniPow;
+	use sc_consensus_pow::MiningMetadata;
+	use sp_consensus_pow::Seal;
+
+	/// Offers a single template and accepts every seal.
+	struct Template(H256);
+
+	impl MiningWorker for Template {
+		fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+			Some(MiningMetadata {
+				best_hash: H256::zero(),
+				pre_hash: self.0,
+				pre_runtime: None,
+				difficulty: U256::one(),
+			})
+		}
+
+		fn submit(&self, _seal: Seal) -> BoxFuture<'static, bool> {
+			async { true }.boxed()
+		}
+	}
+
+	fn pool(pre_hash: H256) -> Pool {
+		Pool {
+			worker: Arc::new(Template(pre_hash)),
+			algorithm: NodePowAlgorithm::MiniPow(MiniPow::default()),
+			share_difficulty: U256::one(),
+			state: Default::default(),
+		}
+	}
+
+	#[test]
+	fn full_share_sets_only_take_blocks() {
+		let pre_hash = H256::repeat_byte(7);
+		let pool = pool(pre_hash);
+		// Every nonce meets the share difficulty, but none the block difficulty of `shares`.
+		let shares = Job { id: 1, pre_hash, difficulty: U256::MAX };
+		let blocks = Job { difficulty: U256::one(), ..shares.clone() };
+		{
+			let mut state = pool.state();
+			state.job_id = shares.id;
+			state.nonces = (0..MAX_SHARES_PER_JOB).map(U256::from).collect();
+		}
+
+		let submit = |job: &Job, nonce: usize| block_on(pool.submit(job, "1", U256::from(nonce)));
+		assert_eq!(submit(&shares, 0), Err(ErrorCode::DuplicateShare));
+		assert_eq!(submit(&shares, MAX_SHARES_PER_JOB), Err(ErrorCode::TooManyShares));
+		assert_eq!(submit(&blocks, MAX_SHARES_PER_JOB), Ok(true));
+		assert_eq!(submit(&shares, 0), Err(ErrorCode::DuplicateShare));
+	}
+
+	#[test]
+	fn shares_are_taken_once() {
+		let pre_hash = H256::repeat_byte(7);
+		let pool = pool(pre_hash);
+		let job = Job { id: 1, pre_hash, difficulty: U256::MAX };
+
+		assert_eq!(block_on(pool.submit(&job, "2", U256::zero())), Err(ErrorCode::JobNotFound));
+		assert_eq!(block_on(pool.submit(&job, "1", U256::zero())), Ok(false));
+		assert_eq!(block_on(pool.submit(&job, "1", U256::zero())), Err(ErrorCode::DuplicateShare));
+		// A new job forgets the shares of the last one.
+		let next = Job { id: 2, ..job };
+		assert_eq!(block_on(pool.submit(&next, "2", U256::zero())), Ok(false));
+	}
+
+	#[test]
+	fn request_lines_are_bounded() {
+		let long = vec![b'x'; MAX_LINE_LENGTH + 1];
+		let input = [&b"{}\n"[..], &long, b"\n"].concat();
+		let (mut reader, mut line) = (&input[..], Vec::new());
+
+		assert!(block_on(read_line(&mut reader, &mut line)).unwrap());
+		assert_eq!(line, b"{}\n");
+		line.clear();
+		let err = block_on(read_line(&mut reader, &mut line)).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		// The longest line allowed is read whole, and the stream may end without a newline.
+		let (mut reader, mut line) = (&long[1..], Vec::new());
+		assert!(!block_on(read_line(&mut reader, &mut line)).unwrap());
+		assert_eq!(line.len(), MAX_LINE_LENGTH);
+	}
+}
@@ -0,0 +1,148 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Records the account that mined the current block. PoW has no authority set to derive
+/// an author from, so the miner names itself through the mandatory-class `set_author`
+/// inherent, which is covered by the seal like any other extrinsic. The inherent has to name
+/// the author the block claims in its pre-runtime digests, found through `Config::FindAuthor`,
+/// so the recorded author never disagrees with the one rewarded for the block. The author is
+/// cleared at the start of every block and exposed through [`FindAuthor`] for pallets that
+/// look the author up once the inherents have been applied.
+pub use pallet::*;
+
+use frame_support::traits::FindAuthor;
+use sp_inherents::InherentIdentifier;
+use sp_runtime::ConsensusEngineId;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+/// Identifier of the block author inherent data.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"blkauthr";
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::INHERENT_IDENTIFIER;
+	use frame_support::{pallet_prelude::*, traits::FindAuthor};
+	use frame_system::pallet_prelude::*;
+	use sp_inherents::MakeFatalError;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Identifies the author the block claims in its pre-runtime digests.
+		type FindAuthor: FindAuthor<Self::AccountId>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Author of the current block, if the miner named one.
+	#[pallet::storage]
+	#[pallet::getter(fn author)]
+	pub type Author<T: Config> = StorageValue<_, T::AccountId>;
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The author of this block was already set.
+		AuthorAlreadySet,
+		/// The author differs from the one claimed in the pre-runtime digests.
+		AuthorMismatch,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			// The previous block's author stays readable until the next block starts.
+			Author::<T>::kill();
+			T::DbWeight::get().writes(1)
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Record the author of the current block. Inherent only.
+		///
+		/// The author is checked here rather than in `check_inherent`, which only sees the
+		/// inherent data of the importing node and not the digests of the block. A mandatory
+		/// inherent failing makes the whole block invalid.
+		#[pallet::weight((
+			10_000 + T::DbWeight::get().reads_writes(2, 1),
+			DispatchClass::Mandatory
+		))]
+		pub fn set_author(origin: OriginFor<T>, author: T::AccountId) -> DispatchResult {
+			ensure_none(origin)?;
+			ensure!(!Author::<T>::exists(), Error::<T>::AuthorAlreadySet);
+
+			let digest = frame_system::Pallet::<T>::digest();
+			let pre_runtime_digests = digest.logs.iter().filter_map(|item| item.as_pre_runtime());
+			ensure!(
+				T::FindAuthor::find_author(pre_runtime_digests).as_ref() == Some(&author),
+				Error::<T>::AuthorMismatch
+			);
+
+			Author::<T>::put(author);
+			Ok(())
+		}
+	}
+
+	#[pallet::inherent]
+	impl<T: Config> ProvideInherent for Pallet<T> {
+		type Call = Call<T>;
+		type Error = MakeFatalError<()>;
+		const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+		fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+			let author = data.get_data::<T::AccountId>(&INHERENT_IDENTIFIER).ok().flatten()?;
+			Some(Call::set_author { author })
+		}
+
+		fn is_inherent(call: &Self::Call) -> bool {
+			matches!(call, Call::set_author { .. })
+		}
+	}
+}
+
+impl<T: Config> FindAuthor<T::AccountId> for Pallet<T> {
+	fn find_author<'a, I>(_digests: I) -> Option<T::AccountId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		Self::author()
+	}
+}
+
+/// Provides the author of locally mined blocks to the block builder. It has to be the author
+/// of the pre-digest the blocks are mined with. Blocks built without an author are still
+/// valid, they just credit nobody.
+#[cfg(feature = "std")]
+pub struct InherentDataProvider<AccountId>(pub Option<AccountId>);
+
+#[cfg(feature = "std")]
+#[async_trait::async_trait]
+impl<AccountId> sp_inherents::InherentDataProvider for InherentDataProvider<AccountId>
+where
+	AccountId: codec::Encode + Send + Sync,
+{
+	fn provide_inherent_data(
+		&self,
+		inherent_data: &mut sp_inherents::InherentData,
+	) -> Result<(), sp_inherents::Error> {
+		match &self.0 {
+			Some(author) => inherent_data.put_data(INHERENT_IDENTIFIER, author),
+			None => Ok(()),
+		}
+	}
+
+	async fn try_handle_error(
+		&self,
+		_identifier: &InherentIdentifier,
+		_error: &[u8],
+	) -> Option<Result<(), sp_inherents::Error>> {
+		// Authors are checked against the pre-runtime digests when the inherent is applied and
+		// `check_inherent` accepts every author, so there are no errors of ours to handle.
+		None
+	}
+}
@@ -0,0 +1,87 @@
+use crate as pallet_block_author;
+use codec::{Decode, Encode};
+use frame_support::traits::{ConstU16, ConstU64, FindAuthor, Hooks};
+use frame_system as system;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	ConsensusEngineId, Digest, DigestItem,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		BlockAuthor: pallet_block_author::{Pallet, Call, Storage, Inherent},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+/// Engine of the pre-runtime digest blocks claim their author in.
+pub const TEST_ENGINE_ID: ConsensusEngineId = *b"test";
+
+/// Finds the author in a pre-runtime digest holding only the encoded account.
+pub struct PreDigestAuthor;
+
+impl FindAuthor<u64> for PreDigestAuthor {
+	fn find_author<'a, I>(digests: I) -> Option<u64>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		let (_, mut pre_digest) = digests.into_iter().find(|(id, _)| *id == TEST_ENGINE_ID)?;
+		u64::decode(&mut pre_digest).ok()
+	}
+}
+
+impl pallet_block_author::Config for Test {
+	type FindAuthor = PreDigestAuthor;
+}
+
+/// Start block `number`, claiming `author` in its pre-runtime digest.
+pub fn start_block(number: u64, author: Option<u64>) {
+	let logs = author
+		.map(|author| DigestItem::PreRuntime(TEST_ENGINE_ID, author.encode()))
+		.into_iter()
+		.collect();
+	System::initialize(&number, &Default::default(), &Digest { logs });
+	BlockAuthor::on_initialize(number);
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
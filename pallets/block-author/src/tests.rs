@@ -0,0 +1,63 @@
+use crate::{mock::*, Call as BlockAuthorCall, Error, INHERENT_IDENTIFIER};
+use frame_support::{
+	assert_noop, assert_ok,
+	inherent::{InherentData, ProvideInherent},
+	traits::FindAuthor,
+};
+use sp_runtime::DispatchError;
+
+#[test]
+fn inherent_is_created_from_the_author_data() {
+	let mut data = InherentData::new();
+	assert_eq!(BlockAuthor::create_inherent(&data), None);
+
+	data.put_data(INHERENT_IDENTIFIER, &7u64).unwrap();
+	let call = BlockAuthor::create_inherent(&data).unwrap();
+	assert_eq!(call, BlockAuthorCall::set_author { author: 7 });
+	assert!(BlockAuthor::is_inherent(&call));
+}
+
+#[test]
+fn set_author_records_the_author_once() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(7));
+		assert_eq!(BlockAuthor::author(), None);
+		assert_ok!(BlockAuthor::set_author(Origin::none(), 7));
+		assert_eq!(BlockAuthor::author(), Some(7));
+		assert_eq!(BlockAuthor::find_author(None), Some(7));
+
+		assert_noop!(BlockAuthor::set_author(Origin::none(), 8), Error::<Test>::AuthorAlreadySet);
+	});
+}
+
+#[test]
+fn set_author_is_inherent_only() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(BlockAuthor::set_author(Origin::signed(7), 7), DispatchError::BadOrigin);
+		assert_noop!(BlockAuthor::set_author(Origin::root(), 7), DispatchError::BadOrigin);
+	});
+}
+
+#[test]
+fn author_is_cleared_when_the_next_block_starts() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(7));
+		assert_ok!(BlockAuthor::set_author(Origin::none(), 7));
+		start_block(2, Some(8));
+		assert_eq!(BlockAuthor::author(), None);
+		assert_ok!(BlockAuthor::set_author(Origin::none(), 8));
+		assert_eq!(BlockAuthor::author(), Some(8));
+	});
+}
+
+#[test]
+fn set_author_must_match_the_pre_digest_author() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(7));
+		assert_noop!(BlockAuthor::set_author(Origin::none(), 8), Error::<Test>::AuthorMismatch);
+
+		start_block(2, None);
+		assert_noop!(BlockAuthor::set_author(Origin::none(), 7), Error::<Test>::AuthorMismatch);
+		assert_eq!(BlockAuthor::author(), None);
+	});
+}
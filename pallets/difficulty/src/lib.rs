@@ -0,0 +1,113 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Stores the current PoW difficulty and retargets it at the end of every block so that
+/// the average block time converges to `TargetBlockTime`. The node reads the value back
+/// through `sp_consensus_pow::DifficultyApi`.
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use sp_core::U256;
+	use sp_runtime::SaturatedConversion;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_timestamp::Config {
+		/// The block time, in milliseconds, that difficulty adjustment aims for.
+		#[pallet::constant]
+		type TargetBlockTime: Get<u64>;
+
+		/// Only `1 / DampFactor` of the deviation from the target block time is acted upon
+		/// in a single adjustment. `1` disables damping.
+		#[pallet::constant]
+		type DampFactor: Get<u64>;
+
+		/// The largest factor by which the difficulty may rise or fall in a single adjustment.
+		#[pallet::constant]
+		type ClampFactor: Get<u64>;
+
+		/// The difficulty never drops below this value.
+		#[pallet::constant]
+		type MinDifficulty: Get<U256>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// The difficulty the next block has to be mined at.
+	#[pallet::storage]
+	#[pallet::getter(fn difficulty)]
+	pub type CurrentDifficulty<T> = StorageValue<_, U256, ValueQuery>;
+
+	/// Timestamp of the last finalized block, used to measure the block time.
+	#[pallet::storage]
+	#[pallet::getter(fn last_timestamp)]
+	pub type LastTimestamp<T> = StorageValue<_, u64, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub initial_difficulty: U256,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { initial_difficulty: U256::from(1_000_000) }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			CurrentDifficulty::<T>::put(self.initial_difficulty.max(T::MinDifficulty::get()));
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			// Reserve the weight of `on_finalize`.
+			T::DbWeight::get().reads_writes(3, 2)
+		}
+
+		fn on_finalize(_n: BlockNumberFor<T>) {
+			let now = pallet_timestamp::Pallet::<T>::get().saturated_into::<u64>();
+			let last = LastTimestamp::<T>::get();
+			LastTimestamp::<T>::put(now);
+
+			// The first block after genesis has nothing to measure against.
+			if last == 0 {
+				return
+			}
+
+			let adjusted = Self::retarget(Self::difficulty(), now.saturating_sub(last));
+			CurrentDifficulty::<T>::put(adjusted);
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Compute the difficulty that follows `current` after a block took `block_time`
+		/// milliseconds to mine.
+		pub fn retarget(current: U256, block_time: u64) -> U256 {
+			let target = T::TargetBlockTime::get().max(1) as u128;
+			let damp = T::DampFactor::get().max(1) as u128;
+			let clamp = T::ClampFactor::get().max(1) as u128;
+
+			// Move only part of the way from the target towards the observed block time, then
+			// bound the result so a single outlier can't swing the difficulty too far.
+			let damped = (block_time as u128 + (damp - 1) * target) / damp;
+			let bounded = damped.clamp(target / clamp, target * clamp).max(1);
+
+			let adjusted = current.saturating_mul(U256::from(target)) / U256::from(bounded);
+			adjusted.max(T::MinDifficulty::get())
+		}
+	}
+}
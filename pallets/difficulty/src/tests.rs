@@ -0,0 +1,73 @@
+use crate::{mock::*, LastTimestamp};
+use frame_support::traits::Hooks;
+use sp_core::U256;
+
+/// Finalize a block whose timestamp is `now`.
+fn finalize_at(now: u64) {
+	Timestamp::set_timestamp(now);
+	Difficulty::on_finalize(System::block_number());
+	System::set_block_number(System::block_number() + 1);
+}
+
+#[test]
+fn genesis_sets_initial_difficulty() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY));
+	});
+}
+
+#[test]
+fn first_block_only_records_timestamp() {
+	new_test_ext().execute_with(|| {
+		finalize_at(5_000);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY));
+		assert_eq!(LastTimestamp::<Test>::get(), 5_000);
+	});
+}
+
+#[test]
+fn on_target_block_time_keeps_difficulty() {
+	new_test_ext().execute_with(|| {
+		finalize_at(1);
+		finalize_at(1 + TARGET_BLOCK_TIME);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY));
+	});
+}
+
+#[test]
+fn fast_blocks_raise_difficulty() {
+	new_test_ext().execute_with(|| {
+		finalize_at(1);
+		// Half the target, damped by two: the adjusted block time is 3/4 of the target.
+		finalize_at(1 + TARGET_BLOCK_TIME / 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY * 4 / 3));
+	});
+}
+
+#[test]
+fn slow_blocks_lower_difficulty() {
+	new_test_ext().execute_with(|| {
+		finalize_at(1);
+		// Twice the target, damped by two: the adjusted block time is 3/2 of the target.
+		finalize_at(1 + TARGET_BLOCK_TIME * 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY * 2 / 3));
+	});
+}
+
+#[test]
+fn adjustment_is_bounded() {
+	new_test_ext().execute_with(|| {
+		let current = U256::from(INITIAL_DIFFICULTY);
+		// Damping alone limits an instant block to doubling the difficulty...
+		assert_eq!(Difficulty::retarget(current, 0), current * 2);
+		// ...while an arbitrarily slow block is stopped by the clamp factor.
+		assert_eq!(Difficulty::retarget(current, u64::MAX), current / 4);
+	});
+}
+
+#[test]
+fn difficulty_never_drops_below_minimum() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Difficulty::retarget(U256::from(1_500), u64::MAX), MinDifficulty::get());
+	});
+}
@@ -0,0 +1,132 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Keeps the parameters that shape the mining zone in runtime storage, so that zone
+/// coverage can be tuned by governance without a node release. The zone rules themselves
+/// run in the runtime too: the node asks the `GeoMiningApi` runtime API whether a miner
+/// may seal a block, so that changing them is a forkless runtime upgrade.
+pub use pallet::*;
+
+pub use geopow_primitives::{
+	zone::{MiningZoneParams, ZoneBlock, ZoneMode, MAX_OCTAVES, MAX_RESOLUTION},
+	GeoPoint,
+};
+use sp_core::H256;
+
+pub mod migrations;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{migrations, GeoPoint, MiningZoneParams, ZoneBlock, H256};
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use geopow_primitives::zone;
+	use sp_runtime::traits::{UniqueSaturatedInto, Zero};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin allowed to change the mining zone parameters.
+		type ZoneOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	pub struct Pallet<T>(_);
+
+	/// Parameters of the mining zone.
+	#[pallet::storage]
+	#[pallet::getter(fn zone_params)]
+	pub type ZoneParams<T> = StorageValue<_, MiningZoneParams, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub zone_params: MiningZoneParams,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { zone_params: Default::default() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			assert!(self.zone_params.is_valid(), "Invalid genesis mining zone parameters");
+			ZoneParams::<T>::put(self.zone_params);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The mining zone parameters were changed. [params]
+		ZoneParamsSet(MiningZoneParams),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The parameters are out of the supported range.
+		InvalidZoneParams,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			migrations::v1::migrate::<T>()
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Replace the mining zone parameters. They apply to blocks built on top of the
+		/// block that includes this call.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_zone_params(origin: OriginFor<T>, params: MiningZoneParams) -> DispatchResult {
+			T::ZoneOrigin::ensure_origin(origin)?;
+			ensure!(params.is_valid(), Error::<T>::InvalidZoneParams);
+
+			ZoneParams::<T>::put(params);
+			Self::deposit_event(Event::ZoneParamsSet(params));
+			Ok(())
+		}
+	}
+
+	impl<T> Pallet<T>
+	where
+		T: Config + frame_system::Config<Hash = H256>,
+	{
+		/// Everything the zone of the block after the current one derives from, given the
+		/// hash of the current block.
+		pub fn zone_block(parent: H256) -> ZoneBlock {
+			let number = frame_system::Pallet::<T>::block_number();
+			// The genesis hash is only recorded once block one is initialized.
+			let genesis = if number.is_zero() {
+				parent
+			} else {
+				frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero())
+			};
+			let number: u64 = number.unique_saturated_into();
+			ZoneBlock { genesis, parent, number: number.saturating_add(1) }
+		}
+
+		/// Whether a miner at `location` may seal the block after the current one, whose
+		/// hash is `parent`.
+		pub fn is_eligible(parent: H256, location: GeoPoint) -> bool {
+			zone::point_is_in_zone(&Self::zone_block(parent), &Self::zone_params(), location)
+		}
+	}
+}
@@ -0,0 +1,47 @@
+//! Storage migrations of the mining zone parameters.
+
+use crate::{Config, MiningZoneParams, Pallet, ZoneMode, ZoneParams};
+use codec::{Decode, Encode};
+use frame_support::{
+	traits::{Get, GetStorageVersion, StorageVersion},
+	weights::Weight,
+};
+
+/// Version 1 added [`MiningZoneParams::mode`]. Before it every block was reseeded.
+pub mod v1 {
+	use super::*;
+
+	/// The parameters as stored before version 1.
+	#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, Debug)]
+	pub struct OldMiningZoneParams {
+		pub resolution: u32,
+		pub scale: u32,
+		pub threshold: i32,
+		pub octaves: u8,
+	}
+
+	/// Append [`ZoneMode::Reseeded`] to the stored parameters, unless they were already
+	/// migrated.
+	pub fn migrate<T: Config>() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let translated = ZoneParams::<T>::translate::<OldMiningZoneParams, _>(|old| {
+			old.map(|old| MiningZoneParams {
+				resolution: old.resolution,
+				scale: old.scale,
+				threshold: old.threshold,
+				octaves: old.octaves,
+				mode: ZoneMode::Reseeded,
+			})
+		});
+		if translated.is_err() {
+			// Leaving undecodable bytes behind would make every read fall back to the
+			// defaults silently, so replace them with the defaults for good.
+			ZoneParams::<T>::kill();
+		}
+		StorageVersion::new(1).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(2, 2)
+	}
+}
@@ -0,0 +1,139 @@
+use crate::{
+	migrations, mock::*, Error, Event as GeoMiningEvent, GeoPoint, MiningZoneParams, ZoneBlock,
+	ZoneMode, ZoneParams,
+};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::unhashed,
+	traits::{GetStorageVersion, StorageVersion},
+	StorageValue,
+};
+use geopow_primitives::zone;
+use sp_core::H256;
+use sp_runtime::DispatchError;
+
+fn params() -> MiningZoneParams {
+	MiningZoneParams {
+		resolution: 4,
+		scale: 10_000,
+		threshold: 350_000,
+		octaves: 3,
+		mode: ZoneMode::Drifting { period: 60 },
+	}
+}
+
+#[test]
+fn genesis_sets_default_params() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(GeoMining::zone_params(), MiningZoneParams::default());
+	});
+}
+
+#[test]
+fn root_can_set_params() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(GeoMining::set_zone_params(Origin::root(), params()));
+		assert_eq!(GeoMining::zone_params(), params());
+		System::assert_last_event(GeoMiningEvent::ZoneParamsSet(params()).into());
+	});
+}
+
+#[test]
+fn signed_origin_cannot_set_params() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			GeoMining::set_zone_params(Origin::signed(1), params()),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn rejects_out_of_range_params() {
+	new_test_ext().execute_with(|| {
+		for invalid in [
+			MiningZoneParams { resolution: 0, ..params() },
+			MiningZoneParams { resolution: crate::MAX_RESOLUTION + 1, ..params() },
+			MiningZoneParams { scale: 0, ..params() },
+			MiningZoneParams { threshold: 1_000_001, ..params() },
+			MiningZoneParams { threshold: -1_000_001, ..params() },
+			MiningZoneParams { octaves: 0, ..params() },
+			MiningZoneParams { octaves: crate::MAX_OCTAVES + 1, ..params() },
+			MiningZoneParams { mode: ZoneMode::Drifting { period: 0 }, ..params() },
+		] {
+			assert_noop!(
+				GeoMining::set_zone_params(Origin::root(), invalid),
+				Error::<Test>::InvalidZoneParams
+			);
+		}
+	});
+}
+
+#[test]
+fn zone_block_follows_the_chain() {
+	new_test_ext().execute_with(|| {
+		let parent = H256::repeat_byte(7);
+		// At genesis the parent is the genesis block itself.
+		assert_eq!(GeoMining::zone_block(parent), ZoneBlock { genesis: parent, parent, number: 1 });
+
+		let genesis = H256::repeat_byte(1);
+		System::set_block_number(5);
+		frame_system::BlockHash::<Test>::insert(0, genesis);
+		assert_eq!(GeoMining::zone_block(parent), ZoneBlock { genesis, parent, number: 6 });
+	});
+}
+
+#[test]
+fn eligibility_matches_the_zone() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(GeoMining::set_zone_params(Origin::root(), params()));
+		System::set_block_number(5);
+		let parent = H256::repeat_byte(7);
+		let block = GeoMining::zone_block(parent);
+
+		// Every ten degrees, on a zone covering a few percent of the globe.
+		let points: Vec<_> = (-8..=8)
+			.flat_map(|lat| (-17..=17).map(move |lon| (lat * 10_000_000, lon * 10_000_000)))
+			.map(|(lat, lon)| GeoPoint::new(lat, lon))
+			.collect();
+		let eligible =
+			points.iter().filter(|point| GeoMining::is_eligible(parent, **point)).count();
+		assert!(eligible > 0 && eligible < points.len());
+		for point in points {
+			let inside = zone::point_is_in_zone(&block, &params(), point);
+			assert_eq!(GeoMining::is_eligible(parent, point), inside);
+		}
+	});
+}
+
+#[test]
+fn genesis_stores_the_current_storage_version() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(GeoMining::on_chain_storage_version(), StorageVersion::new(1));
+	});
+}
+
+#[test]
+fn migration_to_v1_appends_the_reseeded_mode() {
+	new_test_ext().execute_with(|| {
+		let old = migrations::v1::OldMiningZoneParams {
+			resolution: 4,
+			scale: 10_000,
+			threshold: 350_000,
+			octaves: 3,
+		};
+		unhashed::put_raw(&ZoneParams::<Test>::hashed_key(), &old.encode());
+		StorageVersion::new(0).put::<GeoMining>();
+
+		migrations::v1::migrate::<Test>();
+		let migrated = MiningZoneParams { mode: ZoneMode::Reseeded, ..params() };
+		assert_eq!(GeoMining::zone_params(), migrated);
+		assert_eq!(GeoMining::on_chain_storage_version(), StorageVersion::new(1));
+
+		// Migrating again leaves the new layout alone.
+		assert_ok!(GeoMining::set_zone_params(Origin::root(), params()));
+		migrations::v1::migrate::<Test>();
+		assert_eq!(GeoMining::zone_params(), params());
+	});
+}
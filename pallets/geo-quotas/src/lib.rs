@@ -0,0 +1,257 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Caps how many blocks each geographic region may mine within a rolling window of blocks,
+/// so that no single datacenter region dominates block production. Regions are bounding
+/// boxes or geohash cells. Miners claim where they are located through the PoW pre-digest
+/// and every block counts against the region containing the claimed point, as the regions
+/// are when the block is imported, so that miners keep working when regions are redrawn.
+/// The node checks the claim against the miner's IP address and rejects blocks of regions
+/// that exhausted their quota, through the `GeoQuotasApi` runtime API.
+pub use pallet::*;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub use geopow_primitives::{GeoPoint, Geohash, RegionId};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+/// The area a region covers.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum RegionArea {
+	/// The box between two corners in microdegrees. A box whose western edge lies east of
+	/// its eastern edge spans the antimeridian.
+	BoundingBox { south: i32, west: i32, north: i32, east: i32 },
+	/// A geohash cell. Cells of whole geohash characters, as returned by
+	/// [`Geohash::parse`], have five bits per character.
+	Geohash(Geohash),
+}
+
+impl RegionArea {
+	/// Whether the area lies on the globe.
+	pub fn is_valid(&self) -> bool {
+		match *self {
+			Self::BoundingBox { south, west, north, east } =>
+				(-90_000_000..=north).contains(&south) &&
+					north <= 90_000_000 &&
+					(-180_000_000..=180_000_000).contains(&west) &&
+					(-180_000_000..=180_000_000).contains(&east),
+			Self::Geohash(cell) => cell.is_valid(),
+		}
+	}
+
+	/// Whether `point` lies inside the area.
+	pub fn contains(&self, point: GeoPoint) -> bool {
+		let GeoPoint { lat, lon } = point;
+		match *self {
+			Self::BoundingBox { south, west, north, east } => {
+				let within_longitudes = if west <= east {
+					(west..=east).contains(&lon)
+				} else {
+					lon >= west || lon <= east
+				};
+				(south..=north).contains(&lat) && within_longitudes
+			},
+			Self::Geohash(cell) => cell.contains(point),
+		}
+	}
+}
+
+/// A region and its block quota.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Region {
+	/// Where the region lies.
+	pub area: RegionArea,
+	/// Most blocks the region may mine within any [`Config::QuotaWindow`] consecutive
+	/// blocks.
+	pub quota: u32,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{GeoPoint, Region, RegionId};
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use geopow_primitives::PreDigest;
+	use sp_consensus_pow::POW_ENGINE_ID;
+	use sp_runtime::traits::{CheckedSub, One};
+	use sp_std::vec::Vec;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin allowed to define regions and their quotas.
+		type RegionOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Maximum number of regions.
+		#[pallet::constant]
+		type MaxRegions: Get<u32>;
+
+		/// Number of consecutive blocks the quotas apply to. A window of zero is treated
+		/// as one.
+		#[pallet::constant]
+		type QuotaWindow: Get<Self::BlockNumber>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Regions by id.
+	#[pallet::storage]
+	#[pallet::getter(fn region)]
+	pub type Regions<T> = CountedStorageMap<_, Twox64Concat, RegionId, Region>;
+
+	/// The region claimed by each block within the quota window.
+	#[pallet::storage]
+	#[pallet::getter(fn block_region)]
+	pub type BlockRegions<T: Config> = StorageMap<_, Twox64Concat, T::BlockNumber, RegionId>;
+
+	/// Number of blocks within the quota window that claimed each region.
+	#[pallet::storage]
+	#[pallet::getter(fn mined)]
+	pub type Mined<T> = StorageMap<_, Twox64Concat, RegionId, u32, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub regions: Vec<(RegionId, Region)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { regions: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			assert!(self.regions.len() as u32 <= T::MaxRegions::get(), "Too many genesis regions");
+			for (id, region) in &self.regions {
+				assert!(region.area.is_valid(), "Invalid genesis region area");
+				Regions::<T>::insert(id, region);
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A region was defined or its area and quota replaced. [id, region]
+		RegionSet(RegionId, Region),
+		/// A region was removed. [id]
+		RegionRemoved(RegionId),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The area is not on the globe.
+		InvalidArea,
+		/// No more regions can be defined.
+		TooManyRegions,
+		/// There is no region with this id.
+		UnknownRegion,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+			// The block dropping out of the window no longer counts against its region.
+			if let Some(leaving) = Self::leaving(now) {
+				if let Some(region) = BlockRegions::<T>::take(leaving) {
+					Mined::<T>::mutate(region, |mined| *mined = mined.saturating_sub(1));
+				}
+			}
+			if let Some(region) = Self::claimed_region() {
+				BlockRegions::<T>::insert(now, region);
+				Mined::<T>::mutate(region, |mined| *mined = mined.saturating_add(1));
+			}
+			// Finding the claimed region reads every region.
+			T::DbWeight::get().reads_writes(3 + T::MaxRegions::get() as Weight, 4)
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Define region `id`, or replace its area and quota. Blocks within the window that
+		/// claimed the region keep counting against the new quota.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn set_region(origin: OriginFor<T>, id: RegionId, region: Region) -> DispatchResult {
+			T::RegionOrigin::ensure_origin(origin)?;
+			ensure!(region.area.is_valid(), Error::<T>::InvalidArea);
+			ensure!(
+				Regions::<T>::contains_key(id) || Regions::<T>::count() < T::MaxRegions::get(),
+				Error::<T>::TooManyRegions
+			);
+
+			Regions::<T>::insert(id, region);
+			Self::deposit_event(Event::RegionSet(id, region));
+			Ok(())
+		}
+
+		/// Remove a region. Miners located in it are no longer limited.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn remove_region(origin: OriginFor<T>, id: RegionId) -> DispatchResult {
+			T::RegionOrigin::ensure_origin(origin)?;
+			ensure!(Regions::<T>::contains_key(id), Error::<T>::UnknownRegion);
+
+			Regions::<T>::remove(id);
+			Self::deposit_event(Event::RegionRemoved(id));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The region `location` belongs to: the region with the lowest id containing it, so
+		/// that overlapping regions leave miners no choice.
+		pub fn region_at(location: GeoPoint) -> Option<RegionId> {
+			Regions::<T>::iter()
+				.filter(|(_, region)| region.area.contains(location))
+				.map(|(id, _)| id)
+				.min()
+		}
+
+		/// Whether `region` may mine the block after the current one without exceeding its
+		/// quota. Unknown regions have no quota.
+		pub fn has_quota(region: RegionId) -> bool {
+			let quota = match Regions::<T>::get(region) {
+				Some(region) => region.quota,
+				None => return false,
+			};
+			let next = frame_system::Pallet::<T>::block_number() + One::one();
+			let leaving = match Self::leaving(next) {
+				Some(leaving) => BlockRegions::<T>::get(leaving) == Some(region),
+				None => false,
+			};
+			Mined::<T>::get(region).saturating_sub(leaving as u32) < quota
+		}
+
+		/// The block that drops out of the quota window when block `n` enters it.
+		fn leaving(n: T::BlockNumber) -> Option<T::BlockNumber> {
+			n.checked_sub(&T::QuotaWindow::get().max(One::one()))
+		}
+
+		/// The quota region containing the point claimed in the current block's PoW
+		/// pre-digest.
+		fn claimed_region() -> Option<RegionId> {
+			let digest = frame_system::Pallet::<T>::digest();
+			let (_, mut pre_digest) = digest
+				.logs
+				.iter()
+				.filter_map(|item| item.as_pre_runtime())
+				.find(|(id, _)| *id == POW_ENGINE_ID)?;
+			Self::region_at(PreDigest::decode(&mut pre_digest).ok()?.location.point?)
+		}
+	}
+}
@@ -0,0 +1,105 @@
+use crate::{self as pallet_geo_quotas, GeoPoint, Geohash, Region, RegionArea};
+use codec::Encode;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64, GenesisBuild, Hooks};
+use frame_system as system;
+use geopow_primitives::{LocationClaim, PreDigest};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	Digest, DigestItem,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+/// Blocks the quotas of the mock runtime apply to.
+pub const WINDOW: u64 = 10;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		GeoQuotas: pallet_geo_quotas::{Pallet, Call, Storage, Config, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_geo_quotas::Config for Test {
+	type Event = Event;
+	type RegionOrigin = frame_system::EnsureRoot<u64>;
+	type MaxRegions = ConstU32<3>;
+	type QuotaWindow = ConstU64<WINDOW>;
+}
+
+/// Western Europe, allowed two blocks per window.
+pub fn europe() -> Region {
+	let area = RegionArea::BoundingBox {
+		south: 35_000_000,
+		west: -10_000_000,
+		north: 60_000_000,
+		east: 20_000_000,
+	};
+	Region { area, quota: 2 }
+}
+
+/// The `u33` geohash cell around Berlin, allowed one block per window.
+pub fn berlin() -> Region {
+	Region { area: RegionArea::Geohash(Geohash::parse("u33").unwrap()), quota: 1 }
+}
+
+/// Build genesis storage with [`europe`] as region 2 and [`berlin`] as region 1.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_geo_quotas::GenesisConfig { regions: vec![(2, europe()), (1, berlin())] }
+		.assimilate_storage::<Test>(&mut storage)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = storage.into();
+	// Events are not emitted on block 0.
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// Initialize block `n`, mined by a miner claiming to be at `point`, and run the pallet's
+/// `on_initialize`.
+pub fn start_block(n: u64, point: Option<GeoPoint>) {
+	let pre_digest = PreDigest {
+		author: None,
+		location: LocationClaim::new("203.0.113.7", point),
+		attestations: vec![],
+	};
+	let logs = vec![DigestItem::PreRuntime(POW_ENGINE_ID, pre_digest.encode())];
+	System::initialize(&n, &Default::default(), &Digest { logs });
+	GeoQuotas::on_initialize(n);
+}
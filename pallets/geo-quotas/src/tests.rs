@@ -0,0 +1,160 @@
+use crate::{mock::*, Error, Event as GeoQuotasEvent, GeoPoint, Geohash, Region, RegionArea};
+use frame_support::{assert_noop, assert_ok};
+use sp_runtime::DispatchError;
+
+const BERLIN: GeoPoint = GeoPoint::new(52_520_000, 13_400_000);
+const PARIS: GeoPoint = GeoPoint::new(48_860_000, 2_350_000);
+const TOKYO: GeoPoint = GeoPoint::new(35_680_000, 139_690_000);
+
+#[test]
+fn genesis_defines_regions() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(GeoQuotas::region(1), Some(berlin()));
+		assert_eq!(GeoQuotas::region(2), Some(europe()));
+		assert_eq!(GeoQuotas::region(3), None);
+	});
+}
+
+#[test]
+fn points_belong_to_the_lowest_region_containing_them() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(GeoQuotas::region_at(BERLIN), Some(1));
+		assert_eq!(GeoQuotas::region_at(PARIS), Some(2));
+		assert_eq!(GeoQuotas::region_at(TOKYO), None);
+	});
+}
+
+#[test]
+fn bounding_boxes_may_span_the_antimeridian() {
+	let pacific = RegionArea::BoundingBox {
+		south: -30_000_000,
+		west: 170_000_000,
+		north: 30_000_000,
+		east: -170_000_000,
+	};
+	assert!(pacific.is_valid());
+	assert!(pacific.contains(GeoPoint::new(0, 179_000_000)));
+	assert!(pacific.contains(GeoPoint::new(0, -179_000_000)));
+	assert!(!pacific.contains(GeoPoint::new(0, 0)));
+	assert!(!pacific.contains(GeoPoint::new(40_000_000, 179_000_000)));
+}
+
+#[test]
+fn counts_claimed_blocks_within_the_window() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(PARIS));
+		assert!(GeoQuotas::has_quota(2));
+		start_block(2, Some(PARIS));
+		assert_eq!(GeoQuotas::mined(2), 2);
+		assert!(!GeoQuotas::has_quota(2));
+		// Blocks without a claim, outside every region and of other regions do not count.
+		start_block(3, None);
+		start_block(4, Some(TOKYO));
+		start_block(5, Some(BERLIN));
+		assert!(!GeoQuotas::has_quota(2));
+		assert!(!GeoQuotas::has_quota(1));
+		assert_eq!(GeoQuotas::block_region(4), None);
+	});
+}
+
+#[test]
+fn blocks_count_against_the_regions_as_redrawn() {
+	new_test_ext().execute_with(|| {
+		// A miner keeps claiming the same point while governance redraws the regions.
+		start_block(1, Some(BERLIN));
+		assert_eq!(GeoQuotas::block_region(1), Some(1));
+
+		assert_ok!(GeoQuotas::remove_region(Origin::root(), 1));
+		start_block(2, Some(BERLIN));
+		assert_eq!(GeoQuotas::block_region(2), Some(2));
+
+		let tokyo = RegionArea::Geohash(Geohash::parse("xn7").unwrap());
+		assert_ok!(GeoQuotas::set_region(Origin::root(), 2, Region { area: tokyo, quota: 2 }));
+		start_block(3, Some(BERLIN));
+		assert_eq!(GeoQuotas::block_region(3), None);
+		assert_eq!(GeoQuotas::mined(2), 2);
+	});
+}
+
+#[test]
+fn quota_frees_up_as_blocks_leave_the_window() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(BERLIN));
+		for n in 2..WINDOW {
+			start_block(n, None);
+			assert!(!GeoQuotas::has_quota(1), "block {} is within the window", n);
+		}
+		// Block 1 drops out of the window of the next block.
+		start_block(WINDOW, None);
+		assert!(GeoQuotas::has_quota(1));
+		start_block(WINDOW + 1, None);
+		assert_eq!(GeoQuotas::mined(1), 0);
+		assert_eq!(GeoQuotas::block_region(1), None);
+	});
+}
+
+#[test]
+fn unknown_regions_have_no_quota() {
+	new_test_ext().execute_with(|| {
+		assert!(!GeoQuotas::has_quota(7));
+	});
+}
+
+#[test]
+fn root_can_set_and_remove_regions() {
+	new_test_ext().execute_with(|| {
+		let region = Region { quota: 5, ..europe() };
+		assert_ok!(GeoQuotas::set_region(Origin::root(), 3, region));
+		assert_eq!(GeoQuotas::region(3), Some(region));
+		System::assert_last_event(GeoQuotasEvent::RegionSet(3, region).into());
+
+		assert_ok!(GeoQuotas::remove_region(Origin::root(), 1));
+		assert_eq!(GeoQuotas::region(1), None);
+		assert_eq!(GeoQuotas::region_at(BERLIN), Some(2));
+		System::assert_last_event(GeoQuotasEvent::RegionRemoved(1).into());
+		assert_noop!(GeoQuotas::remove_region(Origin::root(), 1), Error::<Test>::UnknownRegion);
+	});
+}
+
+#[test]
+fn signed_origin_cannot_change_regions() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			GeoQuotas::set_region(Origin::signed(1), 3, europe()),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(GeoQuotas::remove_region(Origin::signed(1), 1), DispatchError::BadOrigin);
+	});
+}
+
+#[test]
+fn rejects_areas_off_the_globe() {
+	new_test_ext().execute_with(|| {
+		for area in [
+			RegionArea::BoundingBox { south: 10, west: 0, north: 0, east: 0 },
+			RegionArea::BoundingBox { south: 0, west: 0, north: 90_000_001, east: 0 },
+			RegionArea::BoundingBox { south: 0, west: -180_000_001, north: 0, east: 0 },
+			RegionArea::Geohash(Geohash { cell: 0, bits: 0 }),
+			RegionArea::Geohash(Geohash { cell: 0, bits: 61 }),
+			RegionArea::Geohash(Geohash { cell: 32, bits: 5 }),
+		] {
+			assert_noop!(
+				GeoQuotas::set_region(Origin::root(), 3, Region { area, quota: 1 }),
+				Error::<Test>::InvalidArea
+			);
+		}
+	});
+}
+
+#[test]
+fn limits_the_number_of_regions() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(GeoQuotas::set_region(Origin::root(), 3, europe()));
+		assert_noop!(
+			GeoQuotas::set_region(Origin::root(), 4, europe()),
+			Error::<Test>::TooManyRegions
+		);
+		// Replacing a region needs no room.
+		assert_ok!(GeoQuotas::set_region(Origin::root(), 1, europe()));
+	});
+}
@@ -0,0 +1,167 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Registers the witnesses that attest to miner locations. A witness is a node at known
+/// coordinates that measures the round-trip time to a miner and signs the result. Since
+/// signals travel at a bounded speed, every attestation caps the distance between the
+/// miner and the witness, which makes a location claim costly to fake. Attestations are
+/// carried in the PoW pre-digest and checked by the node against the witnesses and rules
+/// exposed through the `LocationWitnessApi` runtime API.
+pub use pallet::*;
+
+pub use geopow_primitives::{AttestationContext, GeoPoint, Witness};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{AttestationContext, Witness};
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::UniqueSaturatedInto;
+	use sp_std::vec::Vec;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin allowed to register witnesses and change the attestation rules.
+		type WitnessOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Maximum number of registered witnesses.
+		#[pallet::constant]
+		type MaxWitnesses: Get<u32>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Registered witnesses by account.
+	#[pallet::storage]
+	#[pallet::getter(fn witness)]
+	pub type Witnesses<T: Config> = CountedStorageMap<_, Blake2_128Concat, T::AccountId, Witness>;
+
+	/// Number of distinct witnesses a block needs valid attestations from.
+	#[pallet::storage]
+	#[pallet::getter(fn min_witnesses)]
+	pub type MinWitnesses<T> = StorageValue<_, u32, ValueQuery>;
+
+	/// Age in blocks after which an attestation expires.
+	#[pallet::storage]
+	#[pallet::getter(fn max_attestation_age)]
+	pub type MaxAttestationAge<T> = StorageValue<_, u32, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub witnesses: Vec<(T::AccountId, Witness)>,
+		pub min_witnesses: u32,
+		pub max_attestation_age: u32,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { witnesses: Vec::new(), min_witnesses: 0, max_attestation_age: 0 }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			assert!(
+				self.witnesses.len() as u32 <= T::MaxWitnesses::get(),
+				"Too many genesis witnesses"
+			);
+			for (account, witness) in &self.witnesses {
+				assert!(witness.has_valid_coordinates(), "Invalid genesis witness coordinates");
+				Witnesses::<T>::insert(account, witness);
+			}
+			MinWitnesses::<T>::put(self.min_witnesses);
+			MaxAttestationAge::<T>::put(self.max_attestation_age);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A witness was registered or its details replaced. [witness]
+		WitnessAdded(T::AccountId),
+		/// A witness was removed. [witness]
+		WitnessRemoved(T::AccountId),
+		/// The attestation rules changed. [min_witnesses, max_age]
+		RulesSet(u32, u32),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The witness coordinates are not on the globe.
+		InvalidCoordinates,
+		/// No more witnesses can be registered.
+		TooManyWitnesses,
+		/// The account is not a registered witness.
+		NotWitness,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Register `account` as a witness, or replace its key and coordinates.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn add_witness(
+			origin: OriginFor<T>,
+			account: T::AccountId,
+			witness: Witness,
+		) -> DispatchResult {
+			T::WitnessOrigin::ensure_origin(origin)?;
+			ensure!(witness.has_valid_coordinates(), Error::<T>::InvalidCoordinates);
+			ensure!(
+				Witnesses::<T>::contains_key(&account) ||
+					Witnesses::<T>::count() < T::MaxWitnesses::get(),
+				Error::<T>::TooManyWitnesses
+			);
+
+			Witnesses::<T>::insert(&account, witness);
+			Self::deposit_event(Event::WitnessAdded(account));
+			Ok(())
+		}
+
+		/// Deregister a witness. Its attestations stop counting immediately.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn remove_witness(origin: OriginFor<T>, account: T::AccountId) -> DispatchResult {
+			T::WitnessOrigin::ensure_origin(origin)?;
+			ensure!(Witnesses::<T>::contains_key(&account), Error::<T>::NotWitness);
+
+			Witnesses::<T>::remove(&account);
+			Self::deposit_event(Event::WitnessRemoved(account));
+			Ok(())
+		}
+
+		/// Set how many distinct witnesses must attest to a block's location, and how many
+		/// blocks an attestation stays valid for.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(2))]
+		pub fn set_rules(origin: OriginFor<T>, min_witnesses: u32, max_age: u32) -> DispatchResult {
+			T::WitnessOrigin::ensure_origin(origin)?;
+
+			MinWitnesses::<T>::put(min_witnesses);
+			MaxAttestationAge::<T>::put(max_age);
+			Self::deposit_event(Event::RulesSet(min_witnesses, max_age));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The witnesses and rules, as returned by the runtime API.
+		pub fn attestation_context() -> AttestationContext<T::AccountId> {
+			AttestationContext {
+				witnesses: Witnesses::<T>::iter().collect(),
+				min_witnesses: Self::min_witnesses(),
+				max_age: Self::max_attestation_age(),
+				block_number: frame_system::Pallet::<T>::block_number().unique_saturated_into(),
+			}
+		}
+	}
+}
@@ -0,0 +1,83 @@
+use crate as pallet_location_witness;
+use crate::{GeoPoint, Witness};
+use frame_support::traits::{ConstU16, ConstU32, ConstU64, GenesisBuild};
+use frame_system as system;
+use sp_core::{sr25519, H256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		LocationWitness: pallet_location_witness::{Pallet, Call, Storage, Config<T>, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+pub const MAX_WITNESSES: u32 = 3;
+
+impl pallet_location_witness::Config for Test {
+	type Event = Event;
+	type WitnessOrigin = frame_system::EnsureRoot<u64>;
+	type MaxWitnesses = ConstU32<MAX_WITNESSES>;
+}
+
+/// A witness with the given key seed at the given coordinates in degrees.
+pub fn witness(seed: u8, lat: f64, lon: f64) -> Witness {
+	Witness {
+		key: sr25519::Public::from_raw([seed; 32]),
+		location: GeoPoint::from_degrees(lat, lon),
+	}
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_location_witness::GenesisConfig::<Test> {
+		witnesses: vec![(1, witness(1, 52.52, 13.40))],
+		min_witnesses: 1,
+		max_attestation_age: 10,
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = storage.into();
+	// Events are not emitted on block 0.
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,90 @@
+use crate::{mock::*, AttestationContext, Error, Event as WitnessEvent, Witnesses};
+use frame_support::{assert_noop, assert_ok};
+use sp_runtime::DispatchError;
+
+#[test]
+fn genesis_registers_witnesses_and_rules() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(LocationWitness::witness(1), Some(witness(1, 52.52, 13.40)));
+		assert_eq!(LocationWitness::min_witnesses(), 1);
+		assert_eq!(LocationWitness::max_attestation_age(), 10);
+	});
+}
+
+#[test]
+fn root_can_add_and_remove_witnesses() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(LocationWitness::add_witness(Origin::root(), 2, witness(2, -33.87, 151.21)));
+		System::assert_last_event(WitnessEvent::WitnessAdded(2).into());
+		assert_eq!(Witnesses::<Test>::count(), 2);
+
+		assert_ok!(LocationWitness::remove_witness(Origin::root(), 1));
+		System::assert_last_event(WitnessEvent::WitnessRemoved(1).into());
+		assert_eq!(LocationWitness::witness(1), None);
+		assert_noop!(LocationWitness::remove_witness(Origin::root(), 1), Error::<Test>::NotWitness);
+	});
+}
+
+#[test]
+fn signed_origin_cannot_manage_witnesses() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			LocationWitness::add_witness(Origin::signed(2), 2, witness(2, 0.0, 0.0)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			LocationWitness::remove_witness(Origin::signed(1), 1),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(LocationWitness::set_rules(Origin::signed(1), 0, 0), DispatchError::BadOrigin);
+	});
+}
+
+#[test]
+fn rejects_coordinates_off_the_globe() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			LocationWitness::add_witness(Origin::root(), 2, witness(2, 90.5, 0.0)),
+			Error::<Test>::InvalidCoordinates
+		);
+		assert_noop!(
+			LocationWitness::add_witness(Origin::root(), 2, witness(2, 0.0, -180.5)),
+			Error::<Test>::InvalidCoordinates
+		);
+	});
+}
+
+#[test]
+fn caps_the_number_of_witnesses() {
+	new_test_ext().execute_with(|| {
+		for account in 2..=MAX_WITNESSES as u64 {
+			assert_ok!(LocationWitness::add_witness(Origin::root(), account, witness(2, 0.0, 0.0)));
+		}
+		assert_noop!(
+			LocationWitness::add_witness(Origin::root(), 10, witness(10, 0.0, 0.0)),
+			Error::<Test>::TooManyWitnesses
+		);
+		// Updating an existing witness does not need a free slot.
+		assert_ok!(LocationWitness::add_witness(Origin::root(), 1, witness(1, 48.85, 2.35)));
+		assert_eq!(LocationWitness::witness(1), Some(witness(1, 48.85, 2.35)));
+	});
+}
+
+#[test]
+fn context_reflects_storage() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(LocationWitness::set_rules(Origin::root(), 3, 20));
+		System::assert_last_event(WitnessEvent::RulesSet(3, 20).into());
+		System::set_block_number(7);
+
+		assert_eq!(
+			LocationWitness::attestation_context(),
+			AttestationContext {
+				witnesses: vec![(1, witness(1, 52.52, 13.40))],
+				min_witnesses: 3,
+				max_age: 20,
+				block_number: 7,
+			}
+		);
+	});
+}
@@ -0,0 +1,292 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Pays PoW miners. Every block mints a subsidy for its author that shrinks over time
+/// according to a [`SubsidySchedule`], and [`DealWithFees`] splits transaction fees between
+/// the author, an optional treasury and an optional geo-zone bonus pot, burning the rest.
+/// The author is looked up through `FindAuthor` whenever it is needed rather than kept in
+/// storage. The runtime reads it from `pallet_block_author`, which records the author of the
+/// block author inherent. As inherents are only applied after `on_initialize`, the subsidy
+/// is minted in `on_finalize`. [`PowAuthor`] reads the author the PoW pre-runtime digest
+/// claims.
+pub use pallet::*;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::traits::{Currency, FindAuthor, Imbalance, OnUnbalanced};
+use scale_info::TypeInfo;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Saturating, UniqueSaturatedInto, Zero},
+	ConsensusEngineId, PerThing, Perbill, RuntimeDebug,
+};
+use sp_std::marker::PhantomData;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::NegativeImbalance;
+
+/// How the block subsidy shrinks as the chain grows.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+pub enum SubsidySchedule<BlockNumber> {
+	/// Every block earns the initial subsidy.
+	Constant,
+	/// The subsidy halves every `interval` blocks.
+	Halving { interval: BlockNumber },
+	/// The subsidy shrinks by `rate` every `interval` blocks.
+	Decay { interval: BlockNumber, rate: Perbill },
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> SubsidySchedule<BlockNumber> {
+	/// The subsidy of block `n` when blocks of the first interval earn `initial`.
+	pub fn subsidy<Balance: AtLeast32BitUnsigned + Copy>(
+		&self,
+		initial: Balance,
+		n: BlockNumber,
+	) -> Balance {
+		match *self {
+			Self::Constant => initial,
+			Self::Halving { interval } => {
+				let mut subsidy = initial;
+				for _ in 0..Self::periods(n, interval) {
+					if subsidy.is_zero() {
+						break
+					}
+					subsidy /= 2u32.into();
+				}
+				subsidy
+			},
+			Self::Decay { interval, rate } => rate
+				.left_from_one()
+				.saturating_pow(Self::periods(n, interval) as usize)
+				.mul_floor(initial),
+		}
+	}
+
+	/// Number of whole intervals before block `n`.
+	fn periods(n: BlockNumber, interval: BlockNumber) -> u32 {
+		(n / interval.max(1u32.into())).unique_saturated_into()
+	}
+}
+
+/// Fees distributed in a block, by recipient.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct FeeDistribution<Balance> {
+	/// Fees and tips credited to the block author.
+	pub to_author: Balance,
+	/// Fees handed to the treasury.
+	pub to_treasury: Balance,
+	/// Fees handed to the geo-zone bonus pot.
+	pub to_geo_bonus: Balance,
+	/// Fees nobody received.
+	pub burned: Balance,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy> FeeDistribution<Balance> {
+	fn accrue(&mut self, other: Self) {
+		self.to_author = self.to_author.saturating_add(other.to_author);
+		self.to_treasury = self.to_treasury.saturating_add(other.to_treasury);
+		self.to_geo_bonus = self.to_geo_bonus.saturating_add(other.to_geo_bonus);
+		self.burned = self.burned.saturating_add(other.burned);
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{BalanceOf, FeeDistribution, NegativeImbalanceOf, SubsidySchedule};
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, FindAuthor, Imbalance, OnUnbalanced},
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::{
+		traits::{Saturating, Zero},
+		PerThing, Percent,
+	};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The currency rewards are minted and fees are collected in.
+		type Currency: Currency<Self::AccountId>;
+
+		/// Identifies the author of the current block. It is asked for the author of blocks
+		/// whose inherents have been applied.
+		type FindAuthor: FindAuthor<Self::AccountId>;
+
+		/// Subsidy of every block in the first interval of the schedule.
+		#[pallet::constant]
+		type InitialSubsidy: Get<BalanceOf<Self>>;
+
+		/// How the subsidy shrinks over time.
+		#[pallet::constant]
+		type SubsidySchedule: Get<SubsidySchedule<Self::BlockNumber>>;
+
+		/// Share of transaction fees credited to the block author. Tips always go to the
+		/// author in full.
+		#[pallet::constant]
+		type AuthorFeeShare: Get<Percent>;
+
+		/// Share of transaction fees handed to `Treasury`.
+		#[pallet::constant]
+		type TreasuryFeeShare: Get<Percent>;
+
+		/// Receives the treasury share of fees. `()` burns it.
+		type Treasury: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+		/// Share of transaction fees handed to `GeoBonus`.
+		#[pallet::constant]
+		type GeoBonusFeeShare: Get<Percent>;
+
+		/// Receives the geo-zone bonus share of fees, to be paid out to miners in zones the
+		/// network wants to attract. `()` burns it.
+		type GeoBonus: OnUnbalanced<NegativeImbalanceOf<Self>>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Fees distributed so far in the current block.
+	#[pallet::storage]
+	#[pallet::getter(fn block_fees)]
+	pub type BlockFees<T: Config> = StorageValue<_, FeeDistribution<BalanceOf<T>>, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The block subsidy was minted for the author. [author, amount]
+		SubsidyPaid(T::AccountId, BalanceOf<T>),
+		/// Fees and tips of the block were credited to the author. [author, amount]
+		FeesPaid(T::AccountId, BalanceOf<T>),
+		/// Fees of the block were handed to the treasury. [amount]
+		TreasuryFunded(BalanceOf<T>),
+		/// Fees of the block were handed to the geo-zone bonus pot. [amount]
+		GeoBonusFunded(BalanceOf<T>),
+		/// Fees of the block were burned. [amount]
+		FeesBurned(BalanceOf<T>),
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			// Reserve the weight of `on_finalize`.
+			T::DbWeight::get().reads_writes(4, 4)
+		}
+
+		fn on_finalize(n: BlockNumberFor<T>) {
+			let fees = BlockFees::<T>::take();
+
+			if let Some(author) = Self::author() {
+				let subsidy = T::SubsidySchedule::get().subsidy(T::InitialSubsidy::get(), n);
+				// Dropping the imbalance adds the minted subsidy to the total issuance.
+				let minted = T::Currency::deposit_creating(&author, subsidy).peek();
+				if !minted.is_zero() {
+					Self::deposit_event(Event::SubsidyPaid(author.clone(), minted));
+				}
+				if !fees.to_author.is_zero() {
+					Self::deposit_event(Event::FeesPaid(author, fees.to_author));
+				}
+			}
+			if !fees.to_treasury.is_zero() {
+				Self::deposit_event(Event::TreasuryFunded(fees.to_treasury));
+			}
+			if !fees.to_geo_bonus.is_zero() {
+				Self::deposit_event(Event::GeoBonusFunded(fees.to_geo_bonus));
+			}
+			if !fees.burned.is_zero() {
+				Self::deposit_event(Event::FeesBurned(fees.burned));
+			}
+		}
+
+		fn integrity_test() {
+			let shares =
+				[T::AuthorFeeShare::get(), T::TreasuryFeeShare::get(), T::GeoBonusFeeShare::get()];
+			assert!(
+				shares.iter().map(|share| share.deconstruct() as u32).sum::<u32>() <= 100,
+				"Fee shares must not add up to more than 100%"
+			);
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Author of the current block, if it has one.
+		pub fn author() -> Option<T::AccountId> {
+			let digest = frame_system::Pallet::<T>::digest();
+			let pre_runtime_digests = digest.logs.iter().filter_map(|item| item.as_pre_runtime());
+			T::FindAuthor::find_author(pre_runtime_digests)
+		}
+
+		/// Split the fees of a transaction between the author, the treasury and the geo-zone
+		/// bonus pot, and burn the rest. The author receives `tips` in full.
+		pub(crate) fn distribute_fees(fees: NegativeImbalanceOf<T>, tips: NegativeImbalanceOf<T>) {
+			let total = fees.peek();
+			let (to_treasury, rest) = fees.split(T::TreasuryFeeShare::get().mul_floor(total));
+			let (to_geo_bonus, rest) = rest.split(T::GeoBonusFeeShare::get().mul_floor(total));
+			let (mut to_author, burned) = rest.split(T::AuthorFeeShare::get().mul_floor(total));
+			tips.merge_into(&mut to_author);
+
+			let mut distribution = FeeDistribution {
+				to_author: to_author.peek(),
+				to_treasury: to_treasury.peek(),
+				to_geo_bonus: to_geo_bonus.peek(),
+				burned: burned.peek(),
+			};
+
+			match Self::author() {
+				Some(author) => T::Currency::resolve_creating(&author, to_author),
+				None => {
+					// Nobody to pay, so the author's share is dropped and thereby burned.
+					distribution.burned = distribution.burned.saturating_add(to_author.peek());
+					distribution.to_author = Zero::zero();
+				},
+			}
+			T::Treasury::on_unbalanced(to_treasury);
+			T::GeoBonus::on_unbalanced(to_geo_bonus);
+
+			BlockFees::<T>::mutate(|fees| fees.accrue(distribution));
+		}
+	}
+}
+
+/// Transaction fee handler for `pallet_transaction_payment::CurrencyAdapter` that pays
+/// fees out as configured in this pallet instead of burning them.
+pub struct DealWithFees<T>(PhantomData<T>);
+
+impl<T: Config> OnUnbalanced<NegativeImbalanceOf<T>> for DealWithFees<T> {
+	fn on_unbalanceds<B>(mut fees_then_tips: impl Iterator<Item = NegativeImbalanceOf<T>>) {
+		if let Some(fees) = fees_then_tips.next() {
+			let tips = fees_then_tips.next().unwrap_or_else(NegativeImbalanceOf::<T>::zero);
+			Pallet::<T>::distribute_fees(fees, tips);
+		}
+	}
+
+	fn on_nonzero_unbalanced(fees: NegativeImbalanceOf<T>) {
+		Pallet::<T>::distribute_fees(fees, NegativeImbalanceOf::<T>::zero());
+	}
+}
+
+/// Finds the block author in the PoW pre-runtime digest. The pre-digest the node mines with
+/// starts with the SCALE encoded `Option<AccountId>` of the miner; the location claim and
+/// attestations that follow are of no concern to the runtime.
+pub struct PowAuthor<AccountId>(PhantomData<AccountId>);
+
+impl<AccountId: Decode> FindAuthor<AccountId> for PowAuthor<AccountId> {
+	fn find_author<'a, I>(digests: I) -> Option<AccountId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		let (_, mut pre_digest) = digests.into_iter().find(|(id, _)| *id == POW_ENGINE_ID)?;
+		Option::<AccountId>::decode(&mut pre_digest).ok().flatten()
+	}
+}
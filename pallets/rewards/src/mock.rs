@@ -0,0 +1,121 @@
+use crate::{self as pallet_rewards, SubsidySchedule};
+use codec::Encode;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, Currency, Hooks, OnUnbalanced},
+};
+use frame_system as system;
+use pallet_balances::NegativeImbalance;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	Digest, DigestItem, Percent,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Rewards: pallet_rewards::{Pallet, Storage, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ConstU32<50>;
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+pub const INITIAL_SUBSIDY: u64 = 1_000;
+pub const HALVING_INTERVAL: u64 = 10;
+pub const TREASURY: u64 = 100;
+pub const GEO_BONUS_POT: u64 = 101;
+
+parameter_types! {
+	pub const Schedule: SubsidySchedule<u64> =
+		SubsidySchedule::Halving { interval: HALVING_INTERVAL };
+	pub AuthorFeeShare: Percent = Percent::from_percent(60);
+	pub TreasuryFeeShare: Percent = Percent::from_percent(20);
+	pub GeoBonusFeeShare: Percent = Percent::from_percent(10);
+}
+
+/// Credits the funds it receives to `ACCOUNT`.
+pub struct PayTo<const ACCOUNT: u64>;
+
+impl<const ACCOUNT: u64> OnUnbalanced<NegativeImbalance<Test>> for PayTo<ACCOUNT> {
+	fn on_nonzero_unbalanced(amount: NegativeImbalance<Test>) {
+		Balances::resolve_creating(&ACCOUNT, amount);
+	}
+}
+
+impl pallet_rewards::Config for Test {
+	type Event = Event;
+	type Currency = Balances;
+	type FindAuthor = pallet_rewards::PowAuthor<u64>;
+	type InitialSubsidy = ConstU64<INITIAL_SUBSIDY>;
+	type SubsidySchedule = Schedule;
+	type AuthorFeeShare = AuthorFeeShare;
+	type TreasuryFeeShare = TreasuryFeeShare;
+	type Treasury = PayTo<TREASURY>;
+	type GeoBonusFeeShare = GeoBonusFeeShare;
+	type GeoBonus = PayTo<GEO_BONUS_POT>;
+}
+
+/// A PoW pre-digest naming `author`, followed by data the runtime does not look at.
+pub fn pre_digest(author: Option<u64>) -> DigestItem {
+	DigestItem::PreRuntime(POW_ENGINE_ID, (author, b"location claim".to_vec()).encode())
+}
+
+/// Initialize block `n` with the given digest and run the pallet's `on_initialize`.
+pub fn start_block(n: u64, logs: Vec<DigestItem>) {
+	System::initialize(&n, &Default::default(), &Digest { logs });
+	Rewards::on_initialize(n);
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
@@ -0,0 +1,122 @@
+use crate::{
+	mock::*, DealWithFees, Event as RewardsEvent, FeeDistribution, PowAuthor, SubsidySchedule,
+};
+use codec::Encode;
+use frame_support::traits::{Currency, FindAuthor, Hooks, OnUnbalanced};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::Perbill;
+
+/// Hand a transaction's fee and tip to the fee handler, as `CurrencyAdapter` does.
+fn charge(fee: u64, tip: u64) {
+	DealWithFees::<Test>::on_unbalanceds([Balances::issue(fee), Balances::issue(tip)].into_iter());
+}
+
+#[test]
+fn halving_schedule_halves_every_interval() {
+	let schedule = SubsidySchedule::Halving { interval: 10u32 };
+	assert_eq!(schedule.subsidy(1_000u64, 0), 1_000);
+	assert_eq!(schedule.subsidy(1_000u64, 9), 1_000);
+	assert_eq!(schedule.subsidy(1_000u64, 10), 500);
+	assert_eq!(schedule.subsidy(1_000u64, 25), 250);
+	assert_eq!(schedule.subsidy(1_000u64, 100), 0);
+	assert_eq!(schedule.subsidy(u128::MAX, u32::MAX), 0);
+}
+
+#[test]
+fn decay_schedule_shrinks_geometrically() {
+	let schedule = SubsidySchedule::Decay { interval: 10u32, rate: Perbill::from_percent(10) };
+	assert_eq!(schedule.subsidy(1_000u64, 9), 1_000);
+	assert_eq!(schedule.subsidy(1_000u64, 10), 900);
+	assert_eq!(schedule.subsidy(1_000u64, 20), 810);
+	assert_eq!(SubsidySchedule::Constant.subsidy(1_000u64, 1_000_000u32), 1_000);
+}
+
+#[test]
+fn pow_author_is_read_from_the_pre_digest_prefix() {
+	let other = (*b"aura", 7u64.encode());
+	let digests = [other.clone(), (POW_ENGINE_ID, (Some(3u64), 9u8).encode())];
+	let digests = digests.iter().map(|(id, data)| (*id, &data[..]));
+	assert_eq!(PowAuthor::<u64>::find_author(digests), Some(3));
+
+	let digests = [other, (POW_ENGINE_ID, None::<u64>.encode())];
+	let digests = digests.iter().map(|(id, data)| (*id, &data[..]));
+	assert_eq!(PowAuthor::<u64>::find_author(digests), None);
+}
+
+#[test]
+fn subsidy_is_minted_for_the_author() {
+	new_test_ext().execute_with(|| {
+		start_block(1, vec![pre_digest(Some(1))]);
+		// The author is only known for sure once the inherents have been applied.
+		assert_eq!(Balances::total_issuance(), 0);
+		Rewards::on_finalize(1);
+		assert_eq!(Balances::free_balance(1), INITIAL_SUBSIDY);
+		assert_eq!(Balances::total_issuance(), INITIAL_SUBSIDY);
+		System::assert_last_event(RewardsEvent::SubsidyPaid(1, INITIAL_SUBSIDY).into());
+
+		start_block(HALVING_INTERVAL, vec![pre_digest(Some(2))]);
+		Rewards::on_finalize(HALVING_INTERVAL);
+		assert_eq!(Balances::free_balance(2), INITIAL_SUBSIDY / 2);
+		System::assert_last_event(RewardsEvent::SubsidyPaid(2, INITIAL_SUBSIDY / 2).into());
+	});
+}
+
+#[test]
+fn blocks_without_an_author_mint_nothing() {
+	new_test_ext().execute_with(|| {
+		start_block(1, vec![]);
+		Rewards::on_finalize(1);
+		start_block(2, vec![pre_digest(None)]);
+		Rewards::on_finalize(2);
+		assert_eq!(Balances::total_issuance(), 0);
+		assert!(System::events().is_empty());
+	});
+}
+
+#[test]
+fn fees_are_split_and_tips_go_to_the_author() {
+	new_test_ext().execute_with(|| {
+		start_block(1, vec![pre_digest(Some(1))]);
+		charge(100, 7);
+		DealWithFees::<Test>::on_unbalanced(Balances::issue(100));
+
+		assert_eq!(Balances::free_balance(1), 127);
+		assert_eq!(Balances::free_balance(TREASURY), 40);
+		assert_eq!(Balances::free_balance(GEO_BONUS_POT), 20);
+		assert_eq!(
+			Rewards::block_fees(),
+			FeeDistribution { to_author: 127, to_treasury: 40, to_geo_bonus: 20, burned: 20 }
+		);
+		assert_eq!(Balances::total_issuance(), 187);
+
+		Rewards::on_finalize(1);
+		assert_eq!(Balances::free_balance(1), INITIAL_SUBSIDY + 127);
+		assert_eq!(Rewards::block_fees(), FeeDistribution::default());
+		for event in [
+			RewardsEvent::SubsidyPaid(1, INITIAL_SUBSIDY),
+			RewardsEvent::FeesPaid(1, 127),
+			RewardsEvent::TreasuryFunded(40),
+			RewardsEvent::GeoBonusFunded(20),
+			RewardsEvent::FeesBurned(20),
+		] {
+			System::assert_has_event(event.into());
+		}
+	});
+}
+
+#[test]
+fn author_share_is_burned_without_an_author() {
+	new_test_ext().execute_with(|| {
+		start_block(1, vec![]);
+		charge(100, 7);
+
+		assert_eq!(
+			Rewards::block_fees(),
+			FeeDistribution { to_author: 0, to_treasury: 20, to_geo_bonus: 10, burned: 77 }
+		);
+		assert_eq!(Balances::total_issuance(), 30);
+
+		Rewards::on_finalize(1);
+		System::assert_last_event(RewardsEvent::FeesBurned(77).into());
+	});
+}
@@ -0,0 +1,278 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Decides who votes in GRANDPA. Any account can join the validator set by reserving a
+/// bond, after setting its session keys with `pallet_session`; the set is handed to the
+/// session pallet at every session change. Offences reported through `pallet_offences`,
+/// such as GRANDPA equivocations, slash the offender's bond and remove it from the set.
+/// Bonds stay slashable for `BondingDuration` sessions after a validator leaves.
+pub use pallet::*;
+
+use frame_support::{
+	traits::{Currency, Imbalance, OnUnbalanced, ReservableCurrency, ValidatorRegistration},
+	weights::Weight,
+};
+use sp_runtime::{
+	traits::{Convert, Saturating},
+	Perbill,
+};
+use sp_staking::{
+	offence::{DisableStrategy, OffenceDetails, OnOffenceHandler},
+	SessionIndex,
+};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::NegativeImbalance;
+
+/// A validator together with its bond, as recorded by `pallet_session::historical`.
+pub type IdentificationTuple<T> = (<T as frame_system::Config>::AccountId, BalanceOf<T>);
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{BalanceOf, NegativeImbalanceOf};
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{ReservableCurrency, ValidatorRegistration},
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_staking::SessionIndex;
+	use sp_std::vec::Vec;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The currency validator bonds are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// Amount reserved from every validator and slashed when it misbehaves.
+		#[pallet::constant]
+		type ValidatorBond: Get<BalanceOf<Self>>;
+
+		/// Maximum number of validators. Must not exceed the GRANDPA `MaxAuthorities`.
+		#[pallet::constant]
+		type MaxValidators: Get<u32>;
+
+		/// Number of sessions a bond stays reserved, and slashable, after its validator left.
+		#[pallet::constant]
+		type BondingDuration: Get<SessionIndex>;
+
+		/// Tells whether an account has set its session keys.
+		type ValidatorRegistration: ValidatorRegistration<Self::AccountId>;
+
+		/// Receives slashed bonds. `()` burns them.
+		type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Validators and their bonds. The set takes effect at the next session change.
+	#[pallet::storage]
+	#[pallet::getter(fn bond)]
+	pub type Validators<T: Config> =
+		CountedStorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>>;
+
+	/// Bonds of former validators and the session they can be withdrawn in.
+	#[pallet::storage]
+	#[pallet::getter(fn unbonding)]
+	pub type Unbonding<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (BalanceOf<T>, SessionIndex)>;
+
+	/// Index of the last session a validator set was planned for.
+	#[pallet::storage]
+	#[pallet::getter(fn current_session)]
+	pub type CurrentSession<T> = StorageValue<_, SessionIndex, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub validators: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { validators: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			assert!(
+				self.validators.len() as u32 <= T::MaxValidators::get(),
+				"Too many genesis validators"
+			);
+			let bond = T::ValidatorBond::get();
+			for validator in &self.validators {
+				T::Currency::reserve(validator, bond)
+					.expect("Genesis validators must be able to pay the bond");
+				Validators::<T>::insert(validator, bond);
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An account bonded and joined the validator set. [validator]
+		Registered(T::AccountId),
+		/// A validator left the set, its bond starts unbonding. [validator]
+		Unregistered(T::AccountId),
+		/// A validator was removed from the set for an offence. [validator]
+		Chilled(T::AccountId),
+		/// Part of a bond was slashed. [validator, amount]
+		Slashed(T::AccountId, BalanceOf<T>),
+		/// An unbonded bond was released. [account, amount]
+		Withdrawn(T::AccountId, BalanceOf<T>),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already a validator.
+		AlreadyValidator,
+		/// The account is not a validator.
+		NotValidator,
+		/// No more validators can join.
+		TooManyValidators,
+		/// The account has not set its session keys.
+		NoSessionKeys,
+		/// The account's previous bond is still unbonding.
+		AlreadyUnbonding,
+		/// The account has nothing unbonding.
+		NotUnbonding,
+		/// The bonding duration has not passed yet.
+		StillLocked,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Reserve the validator bond and join the validator set from the next session on.
+		/// Session keys must have been set beforehand.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 3))]
+		pub fn register(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!Validators::<T>::contains_key(&who), Error::<T>::AlreadyValidator);
+			ensure!(!Unbonding::<T>::contains_key(&who), Error::<T>::AlreadyUnbonding);
+			ensure!(
+				Validators::<T>::count() < T::MaxValidators::get(),
+				Error::<T>::TooManyValidators
+			);
+			ensure!(T::ValidatorRegistration::is_registered(&who), Error::<T>::NoSessionKeys);
+
+			let bond = T::ValidatorBond::get();
+			T::Currency::reserve(&who, bond)?;
+			Validators::<T>::insert(&who, bond);
+			Self::deposit_event(Event::Registered(who));
+			Ok(())
+		}
+
+		/// Leave the validator set from the next session on. The bond can be withdrawn once
+		/// `BondingDuration` sessions have passed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn unregister(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let bond = Validators::<T>::take(&who).ok_or(Error::<T>::NotValidator)?;
+
+			Self::start_unbonding(&who, bond);
+			Self::deposit_event(Event::Unregistered(who));
+			Ok(())
+		}
+
+		/// Release a bond whose bonding duration has passed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 2))]
+		pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let (bond, unlock) = Unbonding::<T>::get(&who).ok_or(Error::<T>::NotUnbonding)?;
+			ensure!(Self::current_session() >= unlock, Error::<T>::StillLocked);
+
+			T::Currency::unreserve(&who, bond);
+			Unbonding::<T>::remove(&who);
+			Self::deposit_event(Event::Withdrawn(who, bond));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		pub(crate) fn start_unbonding(who: &T::AccountId, bond: BalanceOf<T>) {
+			let unlock = Self::current_session().saturating_add(T::BondingDuration::get());
+			Unbonding::<T>::insert(who, (bond, unlock));
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Slash up to `amount` from the bond of `who` and remove it from the validator set.
+	fn slash(who: &T::AccountId, amount: BalanceOf<T>) {
+		let bond = match (Validators::<T>::take(who), Unbonding::<T>::get(who)) {
+			(Some(bond), _) => {
+				Self::deposit_event(Event::Chilled(who.clone()));
+				bond
+			},
+			(None, Some((bond, _))) => bond,
+			// The bond was already withdrawn, there is nothing left to slash.
+			(None, None) => return,
+		};
+
+		let (imbalance, _) = T::Currency::slash_reserved(who, amount.min(bond));
+		let slashed = imbalance.peek();
+		T::Slash::on_unbalanced(imbalance);
+
+		// The remaining bond unbonds from now on, even if it was unbonding already.
+		Self::start_unbonding(who, bond.saturating_sub(slashed));
+		Self::deposit_event(Event::Slashed(who.clone(), slashed));
+	}
+}
+
+impl<T: Config> pallet_session::SessionManager<T::AccountId> for Pallet<T> {
+	fn new_session(new_index: SessionIndex) -> Option<Vec<T::AccountId>> {
+		CurrentSession::<T>::put(new_index);
+		// Validators that purged their session keys since registering have nothing to vote
+		// with and sit out until they set new ones.
+		let validators: Vec<_> = Validators::<T>::iter_keys()
+			.filter(|validator| T::ValidatorRegistration::is_registered(validator))
+			.collect();
+		// Finality would stall without voters, so an empty set keeps the current one.
+		(!validators.is_empty()).then(|| validators)
+	}
+
+	fn end_session(_end_index: SessionIndex) {}
+
+	fn start_session(_start_index: SessionIndex) {}
+}
+
+impl<T: Config> OnOffenceHandler<T::AccountId, IdentificationTuple<T>, Weight> for Pallet<T> {
+	fn on_offence(
+		offenders: &[OffenceDetails<T::AccountId, IdentificationTuple<T>>],
+		slash_fraction: &[Perbill],
+		_session: SessionIndex,
+		_disable_strategy: DisableStrategy,
+	) -> Weight {
+		for (details, fraction) in offenders.iter().zip(slash_fraction) {
+			let (offender, exposed_bond) = &details.offender;
+			Self::slash(offender, *fraction * *exposed_bond);
+		}
+		T::DbWeight::get().reads_writes(3, 3).saturating_mul(offenders.len() as Weight)
+	}
+}
+
+/// Identifies a validator by its bond for `pallet_session::historical`, so that offences
+/// are judged against the bond the validator had in the session it misbehaved in.
+pub struct BondOf<T>(PhantomData<T>);
+
+impl<T: Config> Convert<T::AccountId, Option<BalanceOf<T>>> for BondOf<T> {
+	fn convert(who: T::AccountId) -> Option<BalanceOf<T>> {
+		Validators::<T>::get(&who).or_else(|| Unbonding::<T>::get(&who).map(|(bond, _)| bond))
+	}
+}
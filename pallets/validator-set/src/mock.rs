@@ -0,0 +1,117 @@
+use crate as pallet_validator_set;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64, GenesisBuild, ValidatorRegistration};
+use frame_system as system;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+use std::{cell::RefCell, collections::BTreeSet};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		ValidatorSet: pallet_validator_set::{Pallet, Call, Storage, Config<T>, Event<T>},
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ConstU32<50>;
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+pub const BOND: u64 = 100;
+pub const MAX_VALIDATORS: u32 = 3;
+pub const BONDING_DURATION: u32 = 2;
+/// An account that never set its session keys.
+pub const KEYLESS: u64 = 9;
+
+thread_local! {
+	/// Accounts that purged their session keys.
+	static PURGED: RefCell<BTreeSet<u64>> = RefCell::new(BTreeSet::new());
+}
+
+/// Let `who` purge its session keys.
+pub fn purge_keys(who: u64) {
+	PURGED.with(|purged| purged.borrow_mut().insert(who));
+}
+
+/// Every account except [`KEYLESS`] and those passed to [`purge_keys`] has session keys.
+pub struct SessionKeysSet;
+
+impl ValidatorRegistration<u64> for SessionKeysSet {
+	fn is_registered(id: &u64) -> bool {
+		*id != KEYLESS && !PURGED.with(|purged| purged.borrow().contains(id))
+	}
+}
+
+impl pallet_validator_set::Config for Test {
+	type Event = Event;
+	type Currency = Balances;
+	type ValidatorBond = ConstU64<BOND>;
+	type MaxValidators = ConstU32<MAX_VALIDATORS>;
+	type BondingDuration = ConstU32<BONDING_DURATION>;
+	type ValidatorRegistration = SessionKeysSet;
+	type Slash = ();
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	PURGED.with(|purged| purged.borrow_mut().clear());
+	let mut storage = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(1, 1_000), (2, 1_000), (3, 1_000), (4, 1_000), (5, 50), (KEYLESS, 1_000)],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	pallet_validator_set::GenesisConfig::<Test> { validators: vec![1] }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = storage.into();
+	// Events are not emitted on block 0.
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,136 @@
+use crate::{mock::*, BondOf, Error, Event as ValidatorSetEvent, Validators};
+use frame_support::{assert_noop, assert_ok, traits::ReservableCurrency};
+use pallet_session::SessionManager;
+use sp_runtime::{traits::Convert, Perbill};
+use sp_staking::offence::{DisableStrategy, OffenceDetails, OnOffenceHandler};
+
+fn validators(session: u32) -> Option<Vec<u64>> {
+	let mut validators = ValidatorSet::new_session(session)?;
+	validators.sort_unstable();
+	Some(validators)
+}
+
+fn report(offender: u64, fraction: Perbill) {
+	let identification = BondOf::<Test>::convert(offender).unwrap();
+	let details = OffenceDetails { offender: (offender, identification), reporters: vec![] };
+	ValidatorSet::on_offence(&[details], &[fraction], 0, DisableStrategy::WhenSlashable);
+}
+
+#[test]
+fn genesis_validators_are_bonded() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(ValidatorSet::bond(1), Some(BOND));
+		assert_eq!(Balances::reserved_balance(1), BOND);
+		assert_eq!(validators(1), Some(vec![1]));
+	});
+}
+
+#[test]
+fn register_bonds_and_joins_the_next_session() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		System::assert_last_event(ValidatorSetEvent::Registered(2).into());
+		assert_eq!(Balances::reserved_balance(2), BOND);
+		assert_eq!(validators(1), Some(vec![1, 2]));
+
+		assert_noop!(ValidatorSet::register(Origin::signed(2)), Error::<Test>::AlreadyValidator);
+	});
+}
+
+#[test]
+fn register_checks_keys_bond_and_capacity() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ValidatorSet::register(Origin::signed(KEYLESS)), Error::<Test>::NoSessionKeys);
+		assert!(ValidatorSet::register(Origin::signed(5)).is_err());
+		assert_eq!(Balances::reserved_balance(5), 0);
+
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		assert_ok!(ValidatorSet::register(Origin::signed(3)));
+		assert_noop!(ValidatorSet::register(Origin::signed(4)), Error::<Test>::TooManyValidators);
+	});
+}
+
+#[test]
+fn bond_is_released_after_the_bonding_duration() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		validators(1);
+		assert_ok!(ValidatorSet::unregister(Origin::signed(2)));
+		System::assert_last_event(ValidatorSetEvent::Unregistered(2).into());
+		assert_eq!(ValidatorSet::unbonding(2), Some((BOND, 1 + BONDING_DURATION)));
+		assert_eq!(validators(2), Some(vec![1]));
+
+		assert_noop!(ValidatorSet::register(Origin::signed(2)), Error::<Test>::AlreadyUnbonding);
+		assert_noop!(
+			ValidatorSet::withdraw_unbonded(Origin::signed(2)),
+			Error::<Test>::StillLocked
+		);
+
+		validators(1 + BONDING_DURATION);
+		assert_ok!(ValidatorSet::withdraw_unbonded(Origin::signed(2)));
+		System::assert_last_event(ValidatorSetEvent::Withdrawn(2, BOND).into());
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_noop!(
+			ValidatorSet::withdraw_unbonded(Origin::signed(2)),
+			Error::<Test>::NotUnbonding
+		);
+		assert_noop!(ValidatorSet::unregister(Origin::signed(2)), Error::<Test>::NotValidator);
+	});
+}
+
+#[test]
+fn empty_set_keeps_the_current_validators() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::unregister(Origin::signed(1)));
+		assert_eq!(Validators::<Test>::count(), 0);
+		assert_eq!(validators(1), None);
+	});
+}
+
+#[test]
+fn validators_without_session_keys_sit_out() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		purge_keys(2);
+		assert_eq!(validators(1), Some(vec![1]));
+		assert_eq!(ValidatorSet::bond(2), Some(BOND));
+
+		// Nobody left to vote keeps the current set.
+		purge_keys(1);
+		assert_eq!(validators(2), None);
+	});
+}
+
+#[test]
+fn offences_slash_and_chill_the_offender() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		let issuance = Balances::total_issuance();
+
+		report(2, Perbill::from_percent(50));
+		System::assert_has_event(ValidatorSetEvent::Chilled(2).into());
+		System::assert_last_event(ValidatorSetEvent::Slashed(2, BOND / 2).into());
+		assert_eq!(Balances::total_issuance(), issuance - BOND / 2);
+		assert_eq!(Balances::reserved_balance(2), BOND / 2);
+		assert_eq!(ValidatorSet::bond(2), None);
+		assert_eq!(ValidatorSet::unbonding(2), Some((BOND / 2, BONDING_DURATION)));
+		assert_eq!(validators(1), Some(vec![1]));
+	});
+}
+
+#[test]
+fn unbonding_bonds_stay_slashable() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ValidatorSet::register(Origin::signed(2)));
+		let identification = BondOf::<Test>::convert(2);
+		assert_ok!(ValidatorSet::unregister(Origin::signed(2)));
+		assert_eq!(BondOf::<Test>::convert(2), identification);
+
+		// Other reserves of the account are never touched.
+		assert_ok!(Balances::reserve(&2, 10));
+		report(2, Perbill::one());
+		assert_eq!(Balances::reserved_balance(2), 10);
+		assert_eq!(ValidatorSet::unbonding(2), Some((0, BONDING_DURATION)));
+		assert_eq!(BondOf::<Test>::convert(3), None);
+	});
+}
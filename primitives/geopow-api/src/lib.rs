@@ -0,0 +1,43 @@
+//! Runtime APIs the node verifies geo-gated blocks with.
+//!
+//! They are declared apart from the pallets implementing them, so that the node-side
+//! consensus crates only link these declarations and the `no_std` primitives, not FRAME.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_core::H256;
+
+pub use geopow_primitives::{AttestationContext, GeoPoint, MiningZoneParams, RegionId};
+
+sp_api::decl_runtime_apis! {
+	/// Mining zone rules needed by the node to verify geo-gated blocks.
+	///
+	/// Version 2 added [`GeoMiningApi::is_eligible`]. Nodes derive the zone themselves from
+	/// [`GeoMiningApi::zone_params`] at blocks of older runtimes.
+	#[api_version(2)]
+	pub trait GeoMiningApi {
+		/// The parameters of the current mining zone.
+		fn zone_params() -> MiningZoneParams;
+
+		/// Whether a miner at `location` may seal a block on top of `parent`. The API must
+		/// be called at `parent`, whose hash the runtime cannot know by itself.
+		fn is_eligible(parent: H256, location: GeoPoint) -> bool;
+	}
+
+	/// Witness registry needed by the node to verify location attestations.
+	pub trait LocationWitnessApi<AccountId> where AccountId: Codec {
+		/// The witnesses and attestation rules in effect.
+		fn attestation_context() -> AttestationContext<AccountId>;
+	}
+
+	/// Regional block quotas needed by the node to verify the region claimed by a block.
+	pub trait GeoQuotasApi {
+		/// The region `location` belongs to: the region with the lowest id containing it, if
+		/// any.
+		fn region_at(location: GeoPoint) -> Option<RegionId>;
+
+		/// Whether `region` may mine the next block without exceeding its quota.
+		fn has_quota(region: RegionId) -> bool;
+	}
+}
@@ -0,0 +1,138 @@
+//! Proof-of-location attestations signed by registered witnesses, and the witness registry
+//! they are checked against.
+//!
+//! A witness measures the round-trip time to a miner's claimed IP address and signs it.
+//! Signals cannot travel faster than light in fibre, so the measurement bounds the
+//! distance between the witness and the miner.
+
+use crate::GeoPoint;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::AccountId32, sr25519};
+use sp_std::vec::Vec;
+
+/// Distance a signal can cover per microsecond of round-trip time, in kilometres. Light
+/// in optical fibre travels about 200 km per millisecond, and the round trip covers the
+/// distance twice.
+pub const KM_PER_RTT_MICROSECOND: f64 = 0.1;
+
+/// Mean radius of the earth in kilometres.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Prefix of every signed payload, so that attestation signatures cannot be replayed as
+/// anything else.
+const SIGNING_CONTEXT: &[u8] = b"geopow/attestation";
+
+/// A witness's signed round-trip measurement to a miner.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct Attestation {
+	/// Account of the witness that took the measurement.
+	pub witness: AccountId32,
+	/// Measured round-trip time in microseconds.
+	pub rtt_micros: u32,
+	/// Number of the best block when the measurement was taken.
+	pub at: u32,
+	/// Signature of the witness key over [`Attestation::payload`].
+	pub signature: sr25519::Signature,
+}
+
+impl Attestation {
+	/// The bytes a witness signs. They bind the measurement to the miner's account and
+	/// claimed IP, so an attestation cannot be reused for another claim.
+	pub fn payload(
+		witness: &AccountId32,
+		miner: &AccountId32,
+		ip: &[u8],
+		rtt_micros: u32,
+		at: u32,
+	) -> Vec<u8> {
+		(SIGNING_CONTEXT, witness, miner, ip, rtt_micros, at).encode()
+	}
+
+	/// Attest to a measurement with the witness key `pair`.
+	#[cfg(feature = "std")]
+	pub fn sign(
+		pair: &sr25519::Pair,
+		witness: AccountId32,
+		miner: &AccountId32,
+		ip: &[u8],
+		rtt_micros: u32,
+		at: u32,
+	) -> Self {
+		use sp_core::Pair;
+
+		let signature = pair.sign(&Self::payload(&witness, miner, ip, rtt_micros, at));
+		Self { witness, rtt_micros, at, signature }
+	}
+}
+
+/// A registered witness.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Witness {
+	/// Key the witness signs attestations with.
+	pub key: sr25519::Public,
+	/// Where the witness is.
+	pub location: GeoPoint,
+}
+
+impl Witness {
+	/// Whether the coordinates lie on the globe.
+	pub fn has_valid_coordinates(&self) -> bool {
+		self.location.is_valid()
+	}
+}
+
+/// Everything the node needs to check the attestations of a block.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct AttestationContext<AccountId> {
+	/// All registered witnesses.
+	pub witnesses: Vec<(AccountId, Witness)>,
+	/// Number of distinct witnesses a block needs valid attestations from. Zero disables
+	/// the check.
+	pub min_witnesses: u32,
+	/// Age in blocks after which an attestation expires.
+	pub max_age: u32,
+	/// Number of the block the context was read at.
+	pub block_number: u32,
+}
+
+/// Farthest a miner can be from a witness that measured the given round-trip time.
+pub fn max_distance_km(rtt_micros: u32) -> f64 {
+	f64::from(rtt_micros) * KM_PER_RTT_MICROSECOND
+}
+
+/// Great-circle distance between two `(latitude, longitude)` points in degrees.
+pub fn distance_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+	let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+	let half_dlat = (lat2 - lat1) / 2.0;
+	let half_dlon = (lon2 - lon1).to_radians() / 2.0;
+	let (sin_dlat, sin_dlon) = (libm::sin(half_dlat), libm::sin(half_dlon));
+	let a = sin_dlat * sin_dlat + libm::cos(lat1) * libm::cos(lat2) * sin_dlon * sin_dlon;
+	2.0 * EARTH_RADIUS_KM * libm::asin(libm::sqrt(a).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BERLIN: (f64, f64) = (52.52, 13.40);
+	const PARIS: (f64, f64) = (48.86, 2.35);
+
+	#[test]
+	fn measures_great_circle_distances() {
+		assert!((distance_km(BERLIN, PARIS) - 878.0).abs() < 5.0);
+		assert_eq!(distance_km(BERLIN, BERLIN), 0.0);
+		assert!((distance_km((0.0, 179.5), (0.0, -179.5)) - 111.2).abs() < 0.5);
+	}
+
+	#[test]
+	fn round_trips_bound_the_distance() {
+		// Berlin and Paris are ~880 km apart, a 10 ms round trip covers up to 1000 km.
+		assert_eq!(max_distance_km(10_000), 1_000.0);
+		assert!(distance_km(BERLIN, PARIS) <= max_distance_km(10_000));
+		assert!(distance_km(BERLIN, PARIS) > max_distance_km(5_000));
+	}
+}
@@ -0,0 +1,45 @@
+//! Conversions between difficulties and the work they accept.
+
+use sp_core::{H256, U256};
+
+/// Determine whether the given hash satisfies the given difficulty.
+/// The test is done by multiplying the two together. If the product
+/// overflows the bounds of U256, then the product (and thus the hash)
+/// was too high.
+pub fn hash_meets_difficulty(hash: &H256, difficulty: U256) -> bool {
+	let num_hash = U256::from(&hash[..]);
+	let (_, overflowed) = num_hash.overflowing_mul(difficulty);
+
+	!overflowed
+}
+
+/// Highest 64-bit work accepted at `difficulty`, so that a uniformly distributed work is
+/// accepted with a probability of about `1 / difficulty`. Difficulties above `u64::MAX`
+/// only accept a work of zero, a difficulty of zero is treated as one.
+pub fn target(difficulty: U256) -> u64 {
+	let target = U256::from(u64::MAX) / difficulty.max(U256::one());
+	target.low_u64()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn targets_shrink_with_the_difficulty() {
+		assert_eq!(target(U256::zero()), u64::MAX);
+		assert_eq!(target(U256::one()), u64::MAX);
+		assert_eq!(target(U256::from(2)), u64::MAX / 2);
+		assert_eq!(target(U256::from(u64::MAX)), 1);
+		assert_eq!(target(U256::MAX), 0);
+	}
+
+	#[test]
+	fn hashes_meet_difficulties_up_to_their_inverse() {
+		let hash = H256::from_low_u64_be(1 << 16);
+		assert!(hash_meets_difficulty(&hash, U256::one()));
+		assert!(hash_meets_difficulty(&hash, U256::MAX >> 16));
+		assert!(!hash_meets_difficulty(&hash, (U256::MAX >> 16) + 1));
+		assert!(hash_meets_difficulty(&H256::zero(), U256::MAX));
+	}
+}
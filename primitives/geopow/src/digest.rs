@@ -0,0 +1,47 @@
+//! The pre-runtime digest miners inject into their blocks.
+
+use crate::{Attestation, GeoPoint};
+use codec::{Decode, Encode};
+use sp_core::crypto::AccountId32;
+use sp_std::vec::Vec;
+
+/// Identifier of a region with a block quota.
+pub type RegionId = u32;
+
+/// The location a miner claims for the block it is sealing.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct LocationClaim {
+	/// Textual IP address of the miner, e.g. `"203.0.113.7"`.
+	pub ip: Vec<u8>,
+	/// Where the miner's GeoIP database locates `ip`, if it could. The runtime counts the
+	/// block against the quota region containing this point, as the regions are when the
+	/// block is imported. The node checks that the point lies in the region the IP address
+	/// is located in.
+	pub point: Option<GeoPoint>,
+}
+
+impl LocationClaim {
+	pub fn new(ip: &str, point: Option<GeoPoint>) -> Self {
+		Self { ip: ip.as_bytes().to_vec(), point }
+	}
+
+	/// The claimed IP address as a string.
+	#[cfg(feature = "std")]
+	pub fn ip(&self) -> String {
+		String::from_utf8_lossy(&self.ip).into_owned()
+	}
+}
+
+/// Data the mining worker injects into every block as the PoW pre-runtime digest.
+///
+/// The author comes first so that the runtime can read it without knowing the
+/// layout of the rest of the digest.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct PreDigest {
+	/// Account credited with mining the block, if the miner configured one.
+	pub author: Option<AccountId32>,
+	/// Where the miner claims to be.
+	pub location: LocationClaim,
+	/// Round-trip measurements from registered witnesses backing the location claim.
+	pub attestations: Vec<Attestation>,
+}
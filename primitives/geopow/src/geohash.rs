@@ -0,0 +1,343 @@
+//! Geohash cells of points given in microdegrees. Cells are computed with integer math only,
+//! so that the runtime and every node put a point into the same cell.
+//!
+//! A cell of `n` bits is kept as the low `n` bits of a `u64`, alternating longitude and
+//! latitude bits starting with longitude, exactly like the textual geohash where every
+//! character stands for five bits.
+
+use crate::point::GeoPoint;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::vec::Vec;
+
+/// Characters of the textual geohash, one per five bits.
+pub const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Longest supported geohash in characters, whose bits still fit a `u64`.
+pub const MAX_PRECISION: u8 = 12;
+
+/// Most bits of a cell, those of a geohash of [`MAX_PRECISION`] characters.
+pub const MAX_BITS: u8 = 5 * MAX_PRECISION;
+
+/// Half the extent of the longitude and latitude axes in microdegrees.
+const LON_RANGE: i32 = 180_000_000;
+const LAT_RANGE: i32 = 90_000_000;
+
+/// The cell of `bits` bits containing the point at `lat` and `lon` microdegrees. Points
+/// off the globe are clamped onto it, and more than [`MAX_BITS`] bits to that many.
+pub fn cell(lat: i32, lon: i32, bits: u32) -> u64 {
+	let bits = bits.min(MAX_BITS.into());
+	let (lon_bits, lat_bits) = axis_bits(bits);
+	interleave(axis_index(lon, LON_RANGE, lon_bits), axis_index(lat, LAT_RANGE, lat_bits), bits)
+}
+
+/// Number of longitude and latitude bits of a cell of `bits` bits.
+fn axis_bits(bits: u32) -> (u32, u32) {
+	((bits + 1) / 2, bits / 2)
+}
+
+/// The cell of `bits` bits with the given longitude and latitude indices.
+fn interleave(lon_index: u64, lat_index: u64, bits: u32) -> u64 {
+	let (lon_bits, lat_bits) = axis_bits(bits);
+	(0..bits).fold(0, |cell, bit| {
+		let (index, shift) = if bit % 2 == 0 {
+			(lon_index, lon_bits - bit / 2 - 1)
+		} else {
+			(lat_index, lat_bits - bit / 2 - 1)
+		};
+		(cell << 1) | ((index >> shift) & 1)
+	})
+}
+
+/// The longitude and latitude indices of the cell of `bits` bits, undoing [`interleave`].
+fn deinterleave(cell: u64, bits: u32) -> (u64, u64) {
+	(0..bits).fold((0, 0), |(lon_index, lat_index), bit| {
+		let value = (cell >> (bits - bit - 1)) & 1;
+		if bit % 2 == 0 {
+			((lon_index << 1) | value, lat_index)
+		} else {
+			(lon_index, (lat_index << 1) | value)
+		}
+	})
+}
+
+/// Index of the part containing `value` when `[-range, range]` is split into `2^bits` equal
+/// parts. The upper bound belongs to the last part.
+fn axis_index(value: i32, range: i32, bits: u32) -> u64 {
+	let (value, range) = (i64::from(value), i64::from(range));
+	let offset = (value + range).clamp(0, 2 * range) as u128;
+	let index = (offset << bits) / (2 * range) as u128;
+	index.min((1 << bits) - 1) as u64
+}
+
+/// The smallest value of `[-range, range]` with part `index` of `2^bits`, the inverse of
+/// [`axis_index`]. Index `2^bits` gives the value just past the upper bound.
+fn axis_start(index: u64, range: i32, bits: u32) -> i32 {
+	let span = 2 * range as u128;
+	let offset = ((u128::from(index) * span) + (1 << bits) - 1) >> bits;
+	(offset as i64 - i64::from(range)) as i32
+}
+
+/// The first and last value of `[-range, range]` with part `index` of `2^bits`.
+fn axis_bounds(index: u64, range: i32, bits: u32) -> (i32, i32) {
+	let last = if index + 1 >= 1 << bits { range } else { axis_start(index + 1, range, bits) - 1 };
+	(axis_start(index, range, bits), last)
+}
+
+/// The cell of a textual geohash and its number of bits, or `None` if `geohash` is empty,
+/// longer than [`MAX_PRECISION`] or has characters outside the [`ALPHABET`].
+pub fn parse(geohash: &str) -> Option<(u64, u32)> {
+	if geohash.is_empty() || geohash.len() > usize::from(MAX_PRECISION) {
+		return None
+	}
+	geohash.bytes().try_fold((0, 0), |(cell, bits), byte| {
+		let value = ALPHABET.iter().position(|c| *c == byte.to_ascii_lowercase())?;
+		Some(((cell << 5) | value as u64, bits + 5))
+	})
+}
+
+/// The textual geohash of `precision` characters of the point at `lat` and `lon`
+/// microdegrees.
+#[cfg(feature = "std")]
+pub fn encode(lat: i32, lon: i32, precision: u8) -> String {
+	let precision = precision.min(MAX_PRECISION);
+	Geohash::of(GeoPoint::new(lat, lon), 5 * precision).to_text().unwrap_or_default()
+}
+
+/// A geohash cell: the low `bits` bits of `cell`, as computed by [`cell`].
+#[derive(
+	Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, MaxEncodedLen, TypeInfo, Debug,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Geohash {
+	pub cell: u64,
+	pub bits: u8,
+}
+
+/// Directions towards the eight neighbours of a cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+	North,
+	NorthEast,
+	East,
+	SouthEast,
+	South,
+	SouthWest,
+	West,
+	NorthWest,
+}
+
+impl Direction {
+	/// All directions, clockwise from the north.
+	pub const ALL: [Direction; 8] = [
+		Self::North,
+		Self::NorthEast,
+		Self::East,
+		Self::SouthEast,
+		Self::South,
+		Self::SouthWest,
+		Self::West,
+		Self::NorthWest,
+	];
+
+	/// Steps in cells to the north and to the east.
+	fn offset(self) -> (i64, i64) {
+		match self {
+			Self::North => (1, 0),
+			Self::NorthEast => (1, 1),
+			Self::East => (0, 1),
+			Self::SouthEast => (-1, 1),
+			Self::South => (-1, 0),
+			Self::SouthWest => (-1, -1),
+			Self::West => (0, -1),
+			Self::NorthWest => (1, -1),
+		}
+	}
+}
+
+impl Geohash {
+	/// The cell of `bits` bits containing `point`. More than [`MAX_BITS`] bits are clamped
+	/// to that many.
+	pub fn of(point: GeoPoint, bits: u8) -> Self {
+		let bits = bits.min(MAX_BITS);
+		Self { cell: cell(point.lat, point.lon, bits.into()), bits }
+	}
+
+	/// The cell of a textual geohash, see [`parse`].
+	pub fn parse(geohash: &str) -> Option<Self> {
+		parse(geohash).map(|(cell, bits)| Self { cell, bits: bits as u8 })
+	}
+
+	/// Whether the cell has between one and [`MAX_PRECISION`] characters' worth of bits and
+	/// no bits beyond them.
+	pub fn is_valid(&self) -> bool {
+		(1..=MAX_BITS).contains(&self.bits) && self.cell >> self.bits == 0
+	}
+
+	/// Whether `point` lies inside the cell. Invalid cells contain no points.
+	pub fn contains(&self, point: GeoPoint) -> bool {
+		self.is_valid() && Self::of(point, self.bits) == *self
+	}
+
+	/// The south-western and north-eastern corners of the cell, both inclusive, so that the
+	/// cell contains exactly the points between them. Cells narrower than a microdegree may
+	/// contain no point at all, their southern or western corner then lies past the northern
+	/// or eastern one. Invalid cells have no bounds.
+	pub fn bounds(&self) -> Option<(GeoPoint, GeoPoint)> {
+		if !self.is_valid() {
+			return None
+		}
+		let bits = u32::from(self.bits);
+		let (lon_bits, lat_bits) = axis_bits(bits);
+		let (lon_index, lat_index) = deinterleave(self.cell, bits);
+		let (south, north) = axis_bounds(lat_index, LAT_RANGE, lat_bits);
+		let (west, east) = axis_bounds(lon_index, LON_RANGE, lon_bits);
+		Some((GeoPoint::new(south, west), GeoPoint::new(north, east)))
+	}
+
+	/// The adjacent cell of the same size in `direction`, wrapping around the antimeridian.
+	/// There is none beyond the poles, nor for invalid cells.
+	pub fn neighbor(&self, direction: Direction) -> Option<Self> {
+		if !self.is_valid() {
+			return None
+		}
+		let bits = u32::from(self.bits);
+		let (lon_bits, lat_bits) = axis_bits(bits);
+		let (lon_index, lat_index) = deinterleave(self.cell, bits);
+		let (north, east) = direction.offset();
+
+		let lat_index = lat_index as i64 + north;
+		if !(0..1 << lat_bits).contains(&lat_index) {
+			return None
+		}
+		let lon_index = (lon_index as i64 + east).rem_euclid(1 << lon_bits);
+		Some(Self { cell: interleave(lon_index as u64, lat_index as u64, bits), bits: self.bits })
+	}
+
+	/// The distinct cells adjacent to this one, clockwise from the north. Cells next to a
+	/// pole have fewer than eight, and very coarse cells may border the same cell twice.
+	pub fn neighbors(&self) -> Vec<Self> {
+		let mut neighbors = Vec::with_capacity(Direction::ALL.len());
+		for neighbor in Direction::ALL.iter().filter_map(|direction| self.neighbor(*direction)) {
+			if neighbor != *self && !neighbors.contains(&neighbor) {
+				neighbors.push(neighbor);
+			}
+		}
+		neighbors
+	}
+
+	/// The textual geohash of a cell of whole characters.
+	#[cfg(feature = "std")]
+	pub fn to_text(&self) -> Option<String> {
+		if !self.is_valid() || self.bits % 5 != 0 {
+			return None
+		}
+		let text = (0..self.bits / 5)
+			.rev()
+			.map(|index| char::from(ALPHABET[((self.cell >> (5 * index)) & 31) as usize]))
+			.collect();
+		Some(text)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_reference_geohashes() {
+		assert_eq!(encode(57_649_110, 10_407_440, 11), "u4pruydqqvj");
+		assert_eq!(encode(52_520_000, 13_400_000, 5), "u33db");
+		assert_eq!(encode(0, 0, 4), "s000");
+	}
+
+	#[test]
+	fn clamps_the_edges_of_the_globe() {
+		assert_eq!(encode(90_000_000, 180_000_000, 3), "zzz");
+		assert_eq!(encode(-90_000_000, -180_000_000, 3), "000");
+		assert_eq!(encode(95_000_000, 190_000_000, 3), "zzz");
+	}
+
+	#[test]
+	fn parses_what_it_encodes() {
+		assert_eq!(parse("u4p"), Some((cell(57_649_110, 10_407_440, 15), 15)));
+		assert_eq!(parse("U4P"), parse("u4p"));
+		assert_eq!(parse(""), None);
+		assert_eq!(parse("u4pa"), None);
+		assert_eq!(parse("0123456789bcd"), None);
+	}
+
+	fn text(geohash: Option<Geohash>) -> Option<String> {
+		geohash.and_then(|geohash| geohash.to_text())
+	}
+
+	#[test]
+	fn finds_reference_neighbors() {
+		let cell = Geohash::parse("gbsuv").unwrap();
+		let neighbors: Vec<_> = cell.neighbors().iter().map(|n| n.to_text().unwrap()).collect();
+		let expected = ["gbsvj", "gbsvn", "gbsuy", "gbsuw", "gbsut", "gbsus", "gbsuu", "gbsvh"];
+		assert_eq!(neighbors, expected);
+		assert_eq!(text(cell.neighbor(Direction::North)), Some("gbsvj".into()));
+	}
+
+	#[test]
+	fn neighbors_wrap_around_the_antimeridian_but_not_the_poles() {
+		let corner = Geohash::parse("zzz").unwrap();
+		assert_eq!(corner.neighbor(Direction::North), None);
+		assert_eq!(corner.neighbor(Direction::NorthWest), None);
+		assert_eq!(text(corner.neighbor(Direction::East)), Some("bpb".into()));
+		assert_eq!(text(corner.neighbor(Direction::SouthEast)), Some("bp8".into()));
+		assert_eq!(corner.neighbors().len(), 5);
+	}
+
+	#[test]
+	fn bounds_hold_exactly_the_points_of_the_cell() {
+		let cell = Geohash::parse("u33").unwrap();
+		let (south_west, north_east) = cell.bounds().unwrap();
+		assert_eq!(south_west, GeoPoint::new(52_031_250, 12_656_250));
+		assert_eq!(north_east, GeoPoint::new(53_437_499, 14_062_499));
+
+		for bits in [1, 7, 15, 32, 45, 60] {
+			for point in [
+				GeoPoint::new(52_520_000, 13_400_000),
+				GeoPoint::new(-33_490_000, 143_210_000),
+				GeoPoint::new(90_000_000, 180_000_000),
+				GeoPoint::new(-90_000_000, -180_000_000),
+			] {
+				let cell = point.geohash(bits);
+				let (south_west, north_east) = cell.bounds().unwrap();
+				assert!(cell.contains(south_west) && cell.contains(north_east));
+				assert!((south_west.lat..=north_east.lat).contains(&point.lat));
+				assert!((south_west.lon..=north_east.lon).contains(&point.lon));
+				let outside = GeoPoint::new(north_east.lat + 1, north_east.lon);
+				assert!(north_east.lat == 90_000_000 || !cell.contains(outside));
+			}
+		}
+	}
+
+	#[test]
+	fn rejects_invalid_cells() {
+		assert!(!Geohash { cell: 0, bits: 0 }.is_valid());
+		assert!(!Geohash { cell: 0, bits: 61 }.is_valid());
+		assert!(!Geohash { cell: 8, bits: 3 }.is_valid());
+		assert!(!Geohash { cell: 8, bits: 3 }.contains(GeoPoint::default()));
+		assert_eq!(Geohash { cell: 7, bits: 3 }.to_text(), None);
+	}
+
+	#[test]
+	fn clamps_the_number_of_bits() {
+		let point = GeoPoint::new(52_520_000, 13_400_000);
+		assert_eq!(Geohash::of(point, 255), Geohash::of(point, MAX_BITS));
+		assert_eq!(point.geohash(255).bits, MAX_BITS);
+		assert_eq!(cell(point.lat, point.lon, 255), cell(point.lat, point.lon, MAX_BITS.into()));
+
+		let oversized = Geohash { cell: 0, bits: 255 };
+		assert!(!oversized.contains(point));
+		assert_eq!(oversized.bounds(), None);
+		assert_eq!(oversized.neighbor(Direction::North), None);
+		assert!(oversized.neighbors().is_empty());
+		assert_eq!(oversized.to_text(), None);
+	}
+}
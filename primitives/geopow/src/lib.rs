@@ -0,0 +1,25 @@
+//! Primitives of geo-gated proof of work: the seal formats, the pre-runtime digest miners
+//! inject into their blocks, conversions between difficulties and accepted work, fixed-point
+//! locations and geohash cells, and the math deriving the mining zone.
+//!
+//! Everything here is `no_std`, so that the runtime, the node and external miners all
+//! share one definition of what a valid block looks like.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod attestation;
+pub mod difficulty;
+pub mod digest;
+pub mod geohash;
+pub mod mini;
+pub mod point;
+pub mod seal;
+pub mod zone;
+
+pub use attestation::{Attestation, AttestationContext, Witness};
+pub use difficulty::hash_meets_difficulty;
+pub use digest::{LocationClaim, PreDigest, RegionId};
+pub use geohash::Geohash;
+pub use point::GeoPoint;
+pub use seal::{check_seal, Compute, Seal, SealError};
+pub use zone::{MiningZoneParams, ZoneBlock, ZoneMode};
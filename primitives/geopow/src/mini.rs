@@ -0,0 +1,43 @@
+//! The MiniPow seal of development chains. The work is the xxHash64 of `pre_hash || nonce`,
+//! which is cheap enough that a single CPU thread finds blocks at low difficulties, and it
+//! has to stay below the [`target`] of the difficulty.
+
+use crate::{difficulty::target, SealError};
+use codec::{Decode, Encode};
+use sp_core::{hashing::twox_64, U256};
+use sp_std::vec::Vec;
+
+/// The seal: the SCALE encoded `U256` nonce, the format of the default mining worker.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Nonce(pub U256);
+
+impl Nonce {
+	pub fn from_seal(seal: &[u8]) -> Option<Self> {
+		let mut input = seal;
+		U256::decode(&mut input).ok().map(Nonce)
+	}
+}
+
+/// xxHash64 of `pre_hash || nonce`, with the nonce in little-endian.
+pub fn work(pre_hash: &[u8], nonce: U256) -> u64 {
+	let mut data = Vec::with_capacity(pre_hash.len() + 32);
+	data.extend_from_slice(pre_hash);
+	data.extend_from_slice(&[0u8; 32]);
+	nonce.to_little_endian(&mut data[pre_hash.len()..]);
+	u64::from_le_bytes(twox_64(&data))
+}
+
+/// Seal `pre_hash` with `nonce` if the resulting work meets `difficulty`.
+pub fn seal(pre_hash: &[u8], nonce: U256, difficulty: U256) -> Option<Vec<u8>> {
+	(work(pre_hash, nonce) <= target(difficulty)).then(|| Nonce(nonce).encode())
+}
+
+/// Check that `seal` is a MiniPow seal of `pre_hash` whose work meets `difficulty`.
+pub fn check_seal(pre_hash: &[u8], seal: &[u8], difficulty: U256) -> Result<(), SealError> {
+	let Nonce(nonce) = Nonce::from_seal(seal).ok_or(SealError::BadSeal)?;
+	// The work is recomputed from the nonce, so it cannot mismatch the pre-hash.
+	if work(pre_hash, nonce) > target(difficulty) {
+		return Err(SealError::InsufficientWork)
+	}
+	Ok(())
+}
@@ -0,0 +1,119 @@
+//! Fixed-point locations on the globe.
+//!
+//! Coordinates are kept in whole microdegrees, about 11 cm at the equator. Unlike floating
+//! point degrees they encode to the same bytes everywhere and compare exactly, so that the
+//! runtime, the node and external tools agree on where a miner is.
+
+use crate::{attestation, geohash::Geohash};
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::fmt;
+
+/// Microdegrees per degree.
+pub const MICRODEGREES: i32 = 1_000_000;
+
+/// A point on the globe in microdegrees.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, MaxEncodedLen, TypeInfo,
+	Debug,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct GeoPoint {
+	/// Latitude in microdegrees, positive to the north.
+	pub lat: i32,
+	/// Longitude in microdegrees, positive to the east.
+	pub lon: i32,
+}
+
+impl GeoPoint {
+	pub const fn new(lat: i32, lon: i32) -> Self {
+		Self { lat, lon }
+	}
+
+	/// The point nearest to `lat` and `lon` degrees. Values beyond the range of `i32`
+	/// microdegrees saturate, so the result may have to be checked with [`Self::is_valid`].
+	pub fn from_degrees(lat: f64, lon: f64) -> Self {
+		let microdegrees = |degrees: f64| libm::round(degrees * f64::from(MICRODEGREES)) as i32;
+		Self::new(microdegrees(lat), microdegrees(lon))
+	}
+
+	/// The point as `(latitude, longitude)` in degrees.
+	pub fn to_degrees(&self) -> (f64, f64) {
+		let degrees = |microdegrees: i32| f64::from(microdegrees) / f64::from(MICRODEGREES);
+		(degrees(self.lat), degrees(self.lon))
+	}
+
+	/// Whether the point lies on the globe.
+	pub fn is_valid(&self) -> bool {
+		(-90 * MICRODEGREES..=90 * MICRODEGREES).contains(&self.lat) &&
+			(-180 * MICRODEGREES..=180 * MICRODEGREES).contains(&self.lon)
+	}
+
+	/// The geohash cell of `bits` bits containing the point.
+	pub fn geohash(&self, bits: u8) -> Geohash {
+		Geohash::of(*self, bits)
+	}
+
+	/// Great-circle distance to `other` in kilometres.
+	pub fn distance_km(&self, other: &Self) -> f64 {
+		attestation::distance_km(self.to_degrees(), other.to_degrees())
+	}
+
+	/// Great-circle distance to `other` in whole metres, for logic that must not keep
+	/// floats around.
+	pub fn distance_m(&self, other: &Self) -> u32 {
+		libm::round(self.distance_km(other) * 1000.0) as u32
+	}
+}
+
+impl fmt::Display for GeoPoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fn degrees(f: &mut fmt::Formatter, microdegrees: i32) -> fmt::Result {
+			let sign = if microdegrees < 0 { "-" } else { "" };
+			let (abs, unit) = (microdegrees.unsigned_abs(), MICRODEGREES as u32);
+			write!(f, "{}{}.{:06}", sign, abs / unit, abs % unit)
+		}
+
+		degrees(f, self.lat)?;
+		f.write_str(", ")?;
+		degrees(f, self.lon)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BERLIN: GeoPoint = GeoPoint::new(52_520_000, 13_400_000);
+	const PARIS: GeoPoint = GeoPoint::new(48_860_000, 2_350_000);
+
+	#[test]
+	fn converts_degrees_to_the_nearest_microdegree() {
+		assert_eq!(GeoPoint::from_degrees(52.52, 13.40), BERLIN);
+		assert_eq!(GeoPoint::from_degrees(-0.0000004, 0.0000006), GeoPoint::new(0, 1));
+		assert_eq!(GeoPoint::from_degrees(1e12, -1e12), GeoPoint::new(i32::MAX, i32::MIN));
+		assert_eq!(BERLIN.to_degrees(), (52.52, 13.4));
+	}
+
+	#[test]
+	fn tells_points_off_the_globe() {
+		assert!(GeoPoint::new(90_000_000, -180_000_000).is_valid());
+		assert!(!GeoPoint::new(90_000_001, 0).is_valid());
+		assert!(!GeoPoint::new(0, 180_000_001).is_valid());
+	}
+
+	#[test]
+	fn measures_distances() {
+		assert_eq!(BERLIN.distance_m(&PARIS), PARIS.distance_m(&BERLIN));
+		assert!((BERLIN.distance_km(&PARIS) - 878.0).abs() < 5.0);
+		assert_eq!(BERLIN.distance_m(&BERLIN), 0);
+	}
+
+	#[test]
+	fn displays_degrees_exactly() {
+		assert_eq!(BERLIN.to_string(), "52.520000, 13.400000");
+		assert_eq!(GeoPoint::new(-500, -180_000_000).to_string(), "-0.000500, -180.000000");
+	}
+}
@@ -0,0 +1,129 @@
+//! The sha3 seal, and why seals get rejected.
+
+use crate::difficulty::hash_meets_difficulty;
+use codec::{Decode, Encode};
+use sha3::{Digest, Sha3_256};
+use sp_core::{H256, U256};
+use sp_std::fmt;
+
+/// A Seal struct that will be encoded to a Vec<u8> as used as the
+/// `RawSeal` type.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct Seal {
+	pub difficulty: U256,
+	pub work: H256,
+	pub nonce: U256,
+}
+
+/// A not-yet-computed attempt to solve the proof of work. Calling the
+/// compute method will compute the hash and return the seal.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct Compute {
+	pub difficulty: U256,
+	pub pre_hash: H256,
+	pub nonce: U256,
+}
+
+impl Compute {
+	pub fn compute(self) -> Seal {
+		let work = H256::from_slice(Sha3_256::digest(&self.encode()[..]).as_slice());
+
+		Seal { nonce: self.nonce, difficulty: self.difficulty, work }
+	}
+}
+
+/// Why a seal was rejected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SealError {
+	/// The seal does not decode.
+	BadSeal,
+	/// The work does not meet the difficulty.
+	InsufficientWork,
+	/// The work does not follow from the pre-hash, difficulty and nonce.
+	WorkMismatch,
+	/// The pre-digest does not place the miner inside the mining zone.
+	OutsideZone,
+	/// Not enough witnesses attest to the location the miner claims.
+	NotAttested,
+	/// The claimed location lies in another quota region than the one the miner is in.
+	RegionMismatch,
+	/// The miner's region has mined its whole quota within the quota window.
+	QuotaExhausted,
+}
+
+impl SealError {
+	/// Short name of the reason, as used for metric labels.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::BadSeal => "bad_seal",
+			Self::InsufficientWork => "insufficient_work",
+			Self::WorkMismatch => "work_mismatch",
+			Self::OutsideZone => "outside_zone",
+			Self::NotAttested => "not_attested",
+			Self::RegionMismatch => "region_mismatch",
+			Self::QuotaExhausted => "quota_exhausted",
+		}
+	}
+}
+
+impl fmt::Display for SealError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			Self::BadSeal => "seal does not decode",
+			Self::InsufficientWork => "work does not meet the difficulty",
+			Self::WorkMismatch => "work does not match the pre-hash and nonce",
+			Self::OutsideZone => "miner is outside the mining zone",
+			Self::NotAttested => "miner location is not attested by enough witnesses",
+			Self::RegionMismatch => "claimed location is in another quota region than the miner",
+			Self::QuotaExhausted => "quota region of the miner has exhausted its quota",
+		})
+	}
+}
+
+/// Check that `seal` is a sha3 seal of `pre_hash` whose work meets `difficulty`.
+pub fn check_seal(pre_hash: &H256, seal: &[u8], difficulty: U256) -> Result<(), SealError> {
+	let seal = Seal::decode(&mut &seal[..]).map_err(|_| SealError::BadSeal)?;
+
+	// See whether the hash meets the difficulty requirement. If not, fail fast.
+	if !hash_meets_difficulty(&seal.work, difficulty) {
+		return Err(SealError::InsufficientWork)
+	}
+
+	// Make sure the provided work actually comes from the correct pre_hash
+	let compute = Compute { difficulty, pre_hash: *pre_hash, nonce: seal.nonce };
+	if compute.compute() != seal {
+		return Err(SealError::WorkMismatch)
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mine(pre_hash: &H256, difficulty: U256) -> Seal {
+		(0u64..)
+			.map(|nonce| Compute { difficulty, pre_hash: *pre_hash, nonce: nonce.into() }.compute())
+			.find(|seal| hash_meets_difficulty(&seal.work, difficulty))
+			.unwrap()
+	}
+
+	#[test]
+	fn check_seal_tells_rejections_apart() {
+		let pre_hash = H256::repeat_byte(7);
+		let difficulty = U256::from(16);
+		let seal = mine(&pre_hash, difficulty);
+		assert_eq!(check_seal(&pre_hash, &seal.encode(), difficulty), Ok(()));
+
+		assert_eq!(check_seal(&pre_hash, &[1, 2, 3], difficulty), Err(SealError::BadSeal));
+		assert_eq!(
+			check_seal(&pre_hash, &seal.encode(), U256::MAX),
+			Err(SealError::InsufficientWork)
+		);
+		assert_eq!(
+			check_seal(&H256::repeat_byte(8), &seal.encode(), difficulty),
+			Err(SealError::WorkMismatch)
+		);
+	}
+}
@@ -0,0 +1,700 @@
+//! The mining zone: a noise field over the globe, seeded by a block hash and cut at a
+//! threshold. Only miners located in a cell above the threshold may seal the next block.
+//!
+//! By default every block reseeds the field with its parent's hash, so the zone jumps
+//! around the globe from one block to the next. [`ZoneMode::Drifting`] instead samples one
+//! 3D field at a time that advances with the block number, so zones migrate gradually.
+//!
+//! The noise is a self-contained Perlin implementation rather than a library one, so
+//! that every node derives the same zone regardless of dependency versions. Whether a
+//! point lies inside the zone is decided in integer arithmetic only: points are taken in
+//! microdegrees and the noise is computed in Q16 fixed point, so that native and Wasm
+//! runtimes agree bit for bit. Only the helpers describing the zone to people, such as
+//! [`cell_bounds`] and [`coverage`], use floats. The trigonometry of [`coverage`] comes
+//! from `libm`, as `core` offers none without `std`.
+
+use crate::point::{GeoPoint, MICRODEGREES};
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use sp_core::H256;
+use sp_std::{collections::btree_map::BTreeMap, vec, vec::Vec};
+
+/// Highest supported raster resolution, in cells per degree.
+pub const MAX_RESOLUTION: u32 = 100;
+
+/// Highest supported number of noise octaves.
+pub const MAX_OCTAVES: u8 = 8;
+
+/// Parameters that shape the mining zone. Fractional values are stored in millionths so
+/// that they encode deterministically.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct MiningZoneParams {
+	/// Raster cells per degree of latitude and longitude.
+	pub resolution: u32,
+	/// Distance in noise space between neighbouring cells, in millionths.
+	pub scale: u32,
+	/// Cells whose noise value lies above this threshold, in millionths, are inside the zone.
+	/// Noise values range from -1 to 1, so raising the threshold shrinks the zone.
+	pub threshold: i32,
+	/// Number of noise octaves layered on top of each other. More octaves give rougher
+	/// zone borders.
+	pub octaves: u8,
+	/// How the zone moves from one block to the next.
+	pub mode: ZoneMode,
+}
+
+/// How the mining zone moves from one block to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum ZoneMode {
+	/// Every block gets a new field seeded with its parent's hash, so the zone of the next
+	/// block is unknown until its parent is mined.
+	Reseeded,
+	/// One field seeded with the genesis hash is sampled at a time that advances with the
+	/// block number, so zones migrate smoothly and miners can see where they are heading.
+	/// The block number rather than the timestamp drives the time, as miners can pick
+	/// their timestamps.
+	Drifting {
+		/// Number of blocks it takes the time to advance by one noise unit, roughly the
+		/// lifetime of a zone. The field repeats after 256 units.
+		period: u32,
+	},
+}
+
+impl Default for ZoneMode {
+	fn default() -> Self {
+		Self::Reseeded
+	}
+}
+
+impl MiningZoneParams {
+	/// Whether the parameters describe a zone the node can evaluate.
+	pub fn is_valid(&self) -> bool {
+		(1..=MAX_RESOLUTION).contains(&self.resolution) &&
+			self.scale > 0 &&
+			(-1_000_000..=1_000_000).contains(&self.threshold) &&
+			(1..=MAX_OCTAVES).contains(&self.octaves) &&
+			!matches!(self.mode, ZoneMode::Drifting { period: 0 })
+	}
+}
+
+impl Default for MiningZoneParams {
+	/// One cell per degree, noise scale 0.03 and threshold 0.2, reseeded every block.
+	fn default() -> Self {
+		Self {
+			resolution: 1,
+			scale: 30_000,
+			threshold: 200_000,
+			octaves: 1,
+			mode: ZoneMode::Reseeded,
+		}
+	}
+}
+
+/// The block a zone is derived for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ZoneBlock {
+	/// Hash of the genesis block, which seeds drifting zones.
+	pub genesis: H256,
+	/// Hash of the parent, which seeds reseeded zones.
+	pub parent: H256,
+	/// Number of the block.
+	pub number: u64,
+}
+
+/// Fractional zone parameters are stored in millionths.
+const PPM: u64 = 1_000_000;
+
+/// One in the Q16 fixed-point numbers the noise is computed with.
+const ONE: i64 = 1 << 16;
+
+/// Domain separator for the permutation stream, so that it never coincides with other
+/// sha3 hashes of the seed.
+const PERMUTATION_DOMAIN: &[u8] = b"geopow/zone-permutation";
+
+/// A coordinate in noise space: the lattice cell it lies in, modulo the 256 cells after
+/// which the field repeats, and its offset into that cell in Q16 fixed point.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NoiseCoord {
+	cell: u8,
+	offset: i64,
+}
+
+impl NoiseCoord {
+	/// The coordinate `numerator / denominator`, with the offset rounded down.
+	///
+	/// # Panics
+	///
+	/// If `denominator` is zero.
+	pub fn ratio(numerator: u128, denominator: u64) -> Self {
+		let denominator = u128::from(denominator);
+		let cell = (numerator / denominator) as u8;
+		let offset = (((numerator % denominator) << 16) / denominator) as i64;
+		Self { cell, offset }
+	}
+}
+
+/// Gradient noise over the plane, seeded with a 32-byte hash. Noise with a time samples a
+/// 3D field at that time instead.
+#[derive(Clone)]
+pub struct ZoneNoise {
+	/// A permutation of `0..=255`, repeated once so lookups of `index + 1` need no wrapping.
+	permutation: [u8; 512],
+	/// Block number and period of drifting zones, whose ratio is the position on the time
+	/// axis.
+	drift: Option<(u64, u32)>,
+}
+
+impl ZoneNoise {
+	/// Derive the noise from all bytes of `seed`.
+	///
+	/// The permutation is shuffled with Fisher-Yates, drawing randomness from
+	/// `sha3_256(PERMUTATION_DOMAIN ++ seed ++ counter)` for increasing little-endian
+	/// `u32` counters.
+	pub fn new(seed: &H256) -> Self {
+		let mut stream = PermutationStream::new(seed);
+		let mut table = [0u8; 256];
+		for (index, entry) in table.iter_mut().enumerate() {
+			*entry = index as u8;
+		}
+		for index in (1..table.len()).rev() {
+			let other = (stream.next_u32() % (index as u32 + 1)) as usize;
+			table.swap(index, other);
+		}
+
+		let mut permutation = [0u8; 512];
+		permutation[..256].copy_from_slice(&table);
+		permutation[256..].copy_from_slice(&table);
+		Self { permutation, drift: None }
+	}
+
+	/// The noise of the zone of `block`, as selected by the mode of `params`.
+	pub fn for_block(params: &MiningZoneParams, block: &ZoneBlock) -> Self {
+		match params.mode {
+			ZoneMode::Reseeded => Self::new(&block.parent),
+			ZoneMode::Drifting { period } =>
+				Self { drift: Some((block.number, period.max(1))), ..Self::new(&block.genesis) },
+		}
+	}
+
+	/// Noise value at a point, in `[-1, 1]` as Q16 fixed point. The field repeats every
+	/// 256 units.
+	pub fn get(&self, x: NoiseCoord, y: NoiseCoord) -> i64 {
+		let (column, row) = (usize::from(x.cell), usize::from(y.cell));
+		let (x, y) = (x.offset, y.offset);
+		let (u, v) = (fade(x), fade(y));
+
+		let p = &self.permutation;
+		let corner = |dx: usize, dy: usize| p[p[column + dx] as usize + row + dy];
+		lerp(
+			v,
+			lerp(u, gradient(corner(0, 0), x, y), gradient(corner(1, 0), x - ONE, y)),
+			lerp(u, gradient(corner(0, 1), x, y - ONE), gradient(corner(1, 1), x - ONE, y - ONE)),
+		)
+	}
+
+	/// Noise value at a point in space, in `[-1, 1]` as Q16 fixed point. The field repeats
+	/// every 256 units along every axis.
+	pub fn get3(&self, x: NoiseCoord, y: NoiseCoord, z: NoiseCoord) -> i64 {
+		let (column, row, layer) = (usize::from(x.cell), usize::from(y.cell), usize::from(z.cell));
+		let (x, y, z) = (x.offset, y.offset, z.offset);
+		let (u, v, w) = (fade(x), fade(y), fade(z));
+
+		let p = &self.permutation;
+		let corner = |dx: usize, dy: usize, dz: usize| {
+			p[p[p[column + dx] as usize + row + dy] as usize + layer + dz]
+		};
+		let face = |dz: usize, z: i64| {
+			let south = lerp(
+				u,
+				gradient3(corner(0, 0, dz), x, y, z),
+				gradient3(corner(1, 0, dz), x - ONE, y, z),
+			);
+			let north = lerp(
+				u,
+				gradient3(corner(0, 1, dz), x, y - ONE, z),
+				gradient3(corner(1, 1, dz), x - ONE, y - ONE, z),
+			);
+			lerp(v, south, north)
+		};
+		// The corners of the cube can add up to slightly more than one.
+		lerp(w, face(0, z), face(1, z - ONE)).clamp(-ONE, ONE)
+	}
+
+	/// Noise at a point of the plane, at the current time if the noise drifts. `frequency`
+	/// scales the time like the plane coordinates.
+	fn sample(&self, x: NoiseCoord, y: NoiseCoord, frequency: u64) -> i64 {
+		match self.drift {
+			Some((number, period)) => {
+				let time = u128::from(number) * u128::from(frequency);
+				self.get3(x, y, NoiseCoord::ratio(time, period.into()))
+			},
+			None => self.get(x, y),
+		}
+	}
+
+	#[cfg(test)]
+	fn permutation(&self) -> &[u8] {
+		&self.permutation[..256]
+	}
+}
+
+/// Counter-mode sha3 byte stream used to shuffle the permutation.
+struct PermutationStream {
+	seed: H256,
+	counter: u32,
+	block: [u8; 32],
+	offset: usize,
+}
+
+impl PermutationStream {
+	fn new(seed: &H256) -> Self {
+		Self { seed: *seed, counter: 0, block: [0; 32], offset: 32 }
+	}
+
+	fn next_u32(&mut self) -> u32 {
+		if self.offset == self.block.len() {
+			let mut hasher = Sha3_256::new();
+			hasher.update(PERMUTATION_DOMAIN);
+			hasher.update(self.seed.as_bytes());
+			hasher.update(self.counter.to_le_bytes());
+			self.block.copy_from_slice(&hasher.finalize());
+			self.counter += 1;
+			self.offset = 0;
+		}
+		let mut bytes = [0u8; 4];
+		bytes.copy_from_slice(&self.block[self.offset..self.offset + 4]);
+		self.offset += 4;
+		u32::from_le_bytes(bytes)
+	}
+}
+
+/// Product of two Q16 numbers, rounded down.
+fn mul(a: i64, b: i64) -> i64 {
+	(a * b) >> 16
+}
+
+/// Perlin's quintic smoothing curve, `6t^5 - 15t^4 + 10t^3`, for `t` in `[0, 1]`.
+fn fade(t: i64) -> i64 {
+	mul(mul(mul(t, t), t), mul(t, t * 6 - 15 * ONE) + 10 * ONE)
+}
+
+fn lerp(t: i64, a: i64, b: i64) -> i64 {
+	a + mul(t, b - a)
+}
+
+/// Dot product of `(x, y)` with one of eight gradients picked by `hash`.
+fn gradient(hash: u8, x: i64, y: i64) -> i64 {
+	match hash & 7 {
+		0 => x + y,
+		1 => -x + y,
+		2 => x - y,
+		3 => -x - y,
+		4 => x,
+		5 => -x,
+		6 => y,
+		_ => -y,
+	}
+}
+
+/// Dot product of `(x, y, z)` with one of the twelve cube edge gradients picked by `hash`,
+/// four of which appear twice, as in Perlin's improved noise.
+fn gradient3(hash: u8, x: i64, y: i64, z: i64) -> i64 {
+	match hash & 15 {
+		0 | 12 => x + y,
+		1 => -x + y,
+		2 => x - y,
+		3 => -x - y,
+		4 => x + z,
+		5 => -x + z,
+		6 => x - z,
+		7 => -x - z,
+		8 => y + z,
+		9 | 13 => -y + z,
+		10 => y - z,
+		14 => -x + y,
+		_ => -y - z,
+	}
+}
+
+/// Size of the zone raster as `(columns, rows)`.
+pub fn grid_size(params: &MiningZoneParams) -> (u32, u32) {
+	let resolution = params.resolution.max(1);
+	(360 * resolution, 180 * resolution)
+}
+
+/// The raster cell containing a point, as `(column, row)`. Columns count eastwards from
+/// the antimeridian and rows northwards from the south pole.
+pub fn cell_of(params: &MiningZoneParams, point: GeoPoint) -> (u32, u32) {
+	let resolution = i64::from(params.resolution.max(1));
+	let degree = i64::from(MICRODEGREES);
+	let (columns, rows) = grid_size(params);
+	// Longitude wraps around, so 180°E and 180°W share the first column.
+	let column = ((i64::from(point.lon) + 180 * degree) * resolution)
+		.div_euclid(degree)
+		.rem_euclid(i64::from(columns));
+	// Latitude does not, the poles belong to the outermost rows.
+	let row = ((i64::from(point.lat) + 90 * degree) * resolution)
+		.div_euclid(degree)
+		.clamp(0, i64::from(rows) - 1);
+	(column as u32, row as u32)
+}
+
+/// Noise value of a cell in millionths, in `[-1_000_000, 1_000_000]`. Octaves are summed
+/// with halving weights and the average is rounded down.
+pub fn cell_noise(noise: &ZoneNoise, params: &MiningZoneParams, (column, row): (u32, u32)) -> i32 {
+	let octaves = params.octaves.clamp(1, MAX_OCTAVES);
+	let mut total = 0;
+	for octave in 0..octaves {
+		let frequency = 1u64 << octave;
+		let coord = |index: u32| {
+			let position = u128::from(index) * u128::from(params.scale) * u128::from(frequency);
+			NoiseCoord::ratio(position, PPM)
+		};
+		let weight = 1i64 << (octaves - 1 - octave);
+		total += weight * noise.sample(coord(column), coord(row), frequency);
+	}
+	let norm = (1i64 << octaves) - 1;
+	(total * PPM as i64).div_euclid(norm * ONE) as i32
+}
+
+/// Whether a cell lies inside the zone described by `noise` and `params`.
+pub fn cell_is_in_zone(noise: &ZoneNoise, params: &MiningZoneParams, cell: (u32, u32)) -> bool {
+	cell_noise(noise, params, cell) > params.threshold
+}
+
+/// Whether a point lies inside the mining zone of `block`.
+pub fn point_is_in_zone(block: &ZoneBlock, params: &MiningZoneParams, point: GeoPoint) -> bool {
+	let noise = ZoneNoise::for_block(params, block);
+	cell_is_in_zone(&noise, params, cell_of(params, point))
+}
+
+/// Bounds of a cell in degrees, as `(south, west, north, east)`.
+pub fn cell_bounds(params: &MiningZoneParams, (column, row): (u32, u32)) -> (f64, f64, f64, f64) {
+	let size = 1.0 / f64::from(params.resolution.max(1));
+	let west = f64::from(column) * size - 180.0;
+	let south = f64::from(row) * size - 90.0;
+	(south, west, south + size, west + size)
+}
+
+/// The whole zone of `block`, one entry per cell in row-major order starting at the
+/// south-west corner.
+pub fn raster(block: &ZoneBlock, params: &MiningZoneParams) -> Vec<bool> {
+	let noise = ZoneNoise::for_block(params, block);
+	let (columns, rows) = grid_size(params);
+	(0..rows)
+		.flat_map(|row| (0..columns).map(move |column| (column, row)))
+		.map(|cell| cell_is_in_zone(&noise, params, cell))
+		.collect()
+}
+
+/// Fraction of the earth's surface inside the zone. Cells are weighted by their area on
+/// the sphere, so the small cells near the poles count for less than equatorial ones.
+pub fn coverage(params: &MiningZoneParams, raster: &[bool]) -> f64 {
+	let (columns, _) = grid_size(params);
+	raster
+		.chunks(columns as usize)
+		.enumerate()
+		.map(|(row, cells)| {
+			let (south, _, north, _) = cell_bounds(params, (0, row as u32));
+			let band = libm::sin(north.to_radians()) - libm::sin(south.to_radians());
+			let inside = cells.iter().filter(|inside| **inside).count();
+			band * inside as f64 / f64::from(columns)
+		})
+		.sum::<f64>() /
+		2.0
+}
+
+/// Lengths of the alternating runs of cells outside and inside the zone. The first run
+/// counts cells outside the zone and is empty if the raster starts inside it.
+pub fn run_lengths(raster: &[bool]) -> Vec<u32> {
+	let mut runs = vec![0];
+	let mut current = false;
+	for &inside in raster {
+		if inside != current {
+			runs.push(0);
+			current = inside;
+		}
+		*runs.last_mut().expect("runs start non-empty; qed") += 1;
+	}
+	runs
+}
+
+/// Cover the zone with non-overlapping rectangles of whole cells, in degrees as
+/// `(south, west, north, east)`. Runs of cells within a row form a rectangle, which
+/// grows northwards while the rows above have a run with the same columns.
+pub fn rectangles(params: &MiningZoneParams, raster: &[bool]) -> Vec<(f64, f64, f64, f64)> {
+	let (columns, _) = grid_size(params);
+	let mut rectangles: Vec<(f64, f64, f64, f64)> = Vec::new();
+	let mut open: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+	for (row, cells) in raster.chunks(columns as usize).enumerate() {
+		let mut next = BTreeMap::new();
+		let mut column = 0;
+		while column < cells.len() {
+			if !cells[column] {
+				column += 1;
+				continue;
+			}
+			let start = column;
+			while column < cells.len() && cells[column] {
+				column += 1;
+			}
+			let run = (start as u32, column as u32 - 1);
+			let (_, _, north, _) = cell_bounds(params, (run.0, row as u32));
+			let index = match open.get(&run) {
+				Some(&index) => {
+					rectangles[index].2 = north;
+					index
+				},
+				None => {
+					let (south, west, _, _) = cell_bounds(params, (run.0, row as u32));
+					let (_, _, _, east) = cell_bounds(params, (run.1, row as u32));
+					rectangles.push((south, west, north, east));
+					rectangles.len() - 1
+				},
+			};
+			next.insert(run, index);
+		}
+		open = next;
+	}
+	rectangles
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(resolution: u32) -> MiningZoneParams {
+		MiningZoneParams { resolution, ..Default::default() }
+	}
+
+	fn at(lat: f64, lon: f64) -> GeoPoint {
+		GeoPoint::from_degrees(lat, lon)
+	}
+
+	#[test]
+	fn maps_points_to_cells() {
+		let params = params(1);
+		assert_eq!(grid_size(&params), (360, 180));
+		assert_eq!(cell_of(&params, at(0.0, 0.0)), (180, 90));
+		assert_eq!(cell_of(&params, at(-0.5, -0.5)), (179, 89));
+		assert_eq!(cell_of(&params, at(52.52, 13.40)), (193, 142));
+		assert_eq!(cell_of(&params, at(-33.87, 151.21)), (331, 56));
+		assert_eq!(cell_of(&params, at(40.71, -74.01)), (105, 130));
+		// Cell borders are exact, one microdegree decides.
+		assert_eq!(cell_of(&params, GeoPoint::new(-1, -1)), (179, 89));
+	}
+
+	#[test]
+	fn wraps_at_the_antimeridian_and_clamps_at_the_poles() {
+		let params = params(4);
+		assert_eq!(cell_of(&params, at(0.0, -180.0)), (0, 360));
+		assert_eq!(cell_of(&params, at(0.0, 180.0)), (0, 360));
+		assert_eq!(cell_of(&params, at(0.0, 179.9)), (1439, 360));
+		assert_eq!(cell_of(&params, at(0.0, 190.0)), cell_of(&params, at(0.0, -170.0)));
+		assert_eq!(cell_of(&params, at(90.0, 0.0)), (720, 719));
+		assert_eq!(cell_of(&params, at(-90.0, 0.0)), (720, 0));
+	}
+
+	#[test]
+	fn splits_noise_coordinates() {
+		assert_eq!(NoiseCoord::ratio(13, 4), NoiseCoord { cell: 3, offset: ONE / 4 });
+		assert_eq!(NoiseCoord::ratio(2, 3), NoiseCoord { cell: 0, offset: 43_690 });
+		// The field repeats every 256 units.
+		assert_eq!(NoiseCoord::ratio(257, 1), NoiseCoord::ratio(1, 1));
+	}
+
+	#[test]
+	fn threshold_controls_coverage() {
+		let noise = ZoneNoise::new(&H256::repeat_byte(7));
+		let covered = |threshold| {
+			let params = MiningZoneParams { threshold, ..Default::default() };
+			let (columns, rows) = grid_size(&params);
+			(0..columns)
+				.flat_map(|column| (0..rows).map(move |row| (column, row)))
+				.filter(|cell| cell_is_in_zone(&noise, &params, *cell))
+				.count()
+		};
+		assert_eq!(covered(1_000_000), 0);
+		assert_eq!(covered(-1_000_000), 360 * 180);
+		assert!(covered(500_000) < covered(200_000));
+	}
+
+	#[test]
+	fn cell_bounds_cover_the_cell() {
+		let params = params(4);
+		let (south, west, north, east) = cell_bounds(&params, cell_of(&params, at(52.52, 13.40)));
+		assert!(south <= 52.52 && 52.52 < north);
+		assert!(west <= 13.40 && 13.40 < east);
+		assert_eq!((north - south, east - west), (0.25, 0.25));
+	}
+
+	#[test]
+	fn coverage_weights_cells_by_area() {
+		let params = params(1);
+		let (columns, rows) = grid_size(&params);
+		let cells = (columns * rows) as usize;
+		assert!((coverage(&params, &vec![true; cells]) - 1.0).abs() < 1e-12);
+		assert_eq!(coverage(&params, &vec![false; cells]), 0.0);
+
+		// The northern hemisphere is half the globe, a polar row much less than its share.
+		let north: Vec<bool> = (0..cells).map(|cell| cell >= cells / 2).collect();
+		assert!((coverage(&params, &north) - 0.5).abs() < 1e-12);
+		let polar: Vec<bool> = (0..cells).map(|cell| cell >= cells - columns as usize).collect();
+		assert!(coverage(&params, &polar) < 1.0 / f64::from(rows) / 10.0);
+	}
+
+	#[test]
+	fn encodes_runs_starting_outside() {
+		assert_eq!(run_lengths(&[]), vec![0]);
+		assert_eq!(run_lengths(&[false, false, true, false]), vec![2, 1, 1]);
+		assert_eq!(run_lengths(&[true, true, false]), vec![0, 2, 1]);
+	}
+
+	#[test]
+	fn merges_stacked_runs_into_rectangles() {
+		let params = params(1);
+		let (columns, rows) = grid_size(&params);
+		let mut cells = vec![false; (columns * rows) as usize];
+		let mut set = |column: u32, row: u32| cells[(row * columns + column) as usize] = true;
+		for row in 90..93 {
+			set(180, row);
+			set(181, row);
+		}
+		set(0, 0);
+		assert_eq!(
+			rectangles(&params, &cells),
+			vec![(-90.0, -180.0, -89.0, -179.0), (0.0, 0.0, 3.0, 2.0)],
+		);
+	}
+
+	// The vectors below pin the zone derivation. Every node, native or Wasm, must compute
+	// exactly these values, so a change to any of them is a consensus break.
+	#[test]
+	fn permutation_golden_vectors() {
+		assert_eq!(
+			&ZoneNoise::new(&H256::zero()).permutation()[..8],
+			&[184, 32, 99, 205, 212, 175, 94, 120],
+		);
+		assert_eq!(
+			&ZoneNoise::new(&H256::repeat_byte(0xab)).permutation()[..8],
+			&[90, 60, 172, 153, 231, 237, 225, 19],
+		);
+	}
+
+	#[test]
+	fn noise_golden_vectors() {
+		let noise = ZoneNoise::new(&H256::repeat_byte(0xab));
+		let half = NoiseCoord::ratio(1, 2);
+		assert_eq!(noise.get(half, half), 8_192);
+		assert_eq!(noise.get(NoiseCoord::ratio(13, 4), NoiseCoord::ratio(31, 4)), 8_128);
+		assert_eq!(noise.get(NoiseCoord::ratio(1001, 10), NoiseCoord::ratio(4242, 100)), 4_119);
+
+		let params = MiningZoneParams { resolution: 2, octaves: 4, ..Default::default() };
+		assert_eq!(cell_noise(&noise, &params, (101, 203)), 73_982);
+		let params = MiningZoneParams::default();
+		assert_eq!(cell_noise(&noise, &params, (359, 179)), 448_471);
+	}
+
+	#[test]
+	fn drifting_noise_golden_vectors() {
+		let noise = ZoneNoise::new(&H256::repeat_byte(0xab));
+		let half = NoiseCoord::ratio(1, 2);
+		assert_eq!(noise.get3(half, half, half), 24_576);
+		let (x, y) = (NoiseCoord::ratio(13, 4), NoiseCoord::ratio(31, 4));
+		assert_eq!(noise.get3(x, y, NoiseCoord::ratio(3, 2)), 35_959);
+		let (x, y) = (NoiseCoord::ratio(1001, 10), NoiseCoord::ratio(4242, 100));
+		assert_eq!(noise.get3(x, y, NoiseCoord::ratio(33, 10)), 27_149);
+
+		let genesis = H256::repeat_byte(0xab);
+		let block = ZoneBlock { genesis, parent: H256::zero(), number: 10 };
+		let mode = ZoneMode::Drifting { period: 7 };
+		let params = MiningZoneParams { mode, ..Default::default() };
+		let noise = ZoneNoise::for_block(&params, &block);
+		assert_eq!(cell_noise(&noise, &params, (359, 179)), -185_273);
+		// Only the ratio of the block number to the period matters.
+		let doubled = MiningZoneParams { mode: ZoneMode::Drifting { period: 14 }, ..params };
+		let noise = ZoneNoise::for_block(&doubled, &ZoneBlock { number: 20, ..block });
+		assert_eq!(cell_noise(&noise, &doubled, (359, 179)), -185_273);
+
+		let block = ZoneBlock { number: 1, ..block };
+		let params = MiningZoneParams {
+			resolution: 2,
+			octaves: 4,
+			mode: ZoneMode::Drifting { period: 4 },
+			..Default::default()
+		};
+		let noise = ZoneNoise::for_block(&params, &block);
+		assert_eq!(cell_noise(&noise, &params, (101, 203)), -101_811);
+	}
+
+	#[test]
+	fn drifting_zones_move_gradually() {
+		let block = |number: u64, parent: u8| ZoneBlock {
+			genesis: H256::repeat_byte(0xab),
+			parent: H256::repeat_byte(parent),
+			number,
+		};
+		let changed = |params: &MiningZoneParams, a: &ZoneBlock, b: &ZoneBlock| {
+			let (a, b) = (raster(a, params), raster(b, params));
+			a.iter().zip(&b).filter(|(a, b)| a != b).count()
+		};
+
+		let reseeded = MiningZoneParams::default();
+		assert!(changed(&reseeded, &block(100, 1), &block(101, 2)) > 10_000);
+		assert_eq!(changed(&reseeded, &block(100, 1), &block(101, 1)), 0);
+
+		// Drifting zones ignore the parent and barely move from one block to the next.
+		let drifting = MiningZoneParams { mode: ZoneMode::Drifting { period: 100 }, ..reseeded };
+		assert_eq!(changed(&drifting, &block(100, 1), &block(100, 2)), 0);
+		assert!(changed(&drifting, &block(100, 1), &block(101, 2)) < 1_000);
+		assert!(changed(&drifting, &block(100, 1), &block(200, 2)) > 10_000);
+	}
+
+	#[test]
+	fn drifting_needs_a_period() {
+		let params = |period| MiningZoneParams {
+			mode: ZoneMode::Drifting { period },
+			..Default::default()
+		};
+		assert!(params(1).is_valid());
+		assert!(!params(0).is_valid());
+	}
+
+	#[test]
+	fn zone_golden_vector() {
+		let noise = ZoneNoise::new(&H256::repeat_byte(0xab));
+		let params = MiningZoneParams::default();
+		let (columns, rows) = grid_size(&params);
+		let covered = (0..columns)
+			.flat_map(|column| (0..rows).map(move |row| (column, row)))
+			.filter(|cell| cell_is_in_zone(&noise, &params, *cell))
+			.count();
+		assert_eq!(covered, 16086);
+	}
+
+	#[test]
+	fn every_seed_byte_matters() {
+		// Permuting the bytes of a hash kept the old character-sum seed unchanged.
+		let mut a = [0u8; 32];
+		a[31] = 1;
+		let mut b = [0u8; 32];
+		b[30] = 1;
+		assert_eq!(&ZoneNoise::new(&H256(a)).permutation()[..4], &[142, 39, 221, 13]);
+		assert_eq!(&ZoneNoise::new(&H256(b)).permutation()[..4], &[64, 225, 158, 161]);
+	}
+
+	#[test]
+	fn noise_stays_in_range() {
+		let noise = ZoneNoise::new(&H256::repeat_byte(3));
+		for step in 0..10_000 {
+			let coord = |factor| NoiseCoord::ratio(step * factor, 10_000);
+			let value = noise.get(coord(137), coord(291));
+			assert!((-ONE..=ONE).contains(&value));
+		}
+	}
+}
@@ -10,11 +10,13 @@ use pallet_grandpa::{
 	fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList,
 };
 use sp_api::impl_runtime_apis;
-use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, U256};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
+	traits::{
+		AccountIdLookup, BlakeTwo256, Block as BlockT, ConvertInto, IdentifyAccount, NumberFor,
+		OpaqueKeys, Verify,
+	},
 	transaction_validity::{TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
@@ -38,15 +40,37 @@ pub use frame_support::{
 pub use frame_system::Call as SystemCall;
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
+use pallet_session::historical as pallet_session_historical;
 use pallet_transaction_payment::CurrencyAdapter;
 use pallet_contracts::DefaultContractAccessWeight;
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
-pub use sp_runtime::{Perbill, Permill};
+pub use sp_runtime::{Perbill, Percent, Permill};
 
 /// Import the template pallet.
 pub use pallet_template;
 
+/// Import the difficulty adjustment pallet.
+pub use pallet_difficulty;
+
+/// Import the mining zone pallet.
+pub use pallet_geo_mining;
+
+/// Import the location witness pallet.
+pub use pallet_location_witness;
+
+/// Import the regional mining quotas pallet.
+pub use pallet_geo_quotas;
+
+/// Import the block author pallet.
+pub use pallet_block_author;
+
+/// Import the block rewards pallet.
+pub use pallet_rewards;
+
+/// Import the validator set pallet.
+pub use pallet_validator_set;
+
 /// An index to a block.
 pub type BlockNumber = u32;
 
@@ -84,7 +108,6 @@ pub mod opaque {
 
 	impl_opaque_keys! {
 		pub struct SessionKeys {
-			pub aura: Aura,
 			pub grandpa: Grandpa,
 		}
 	}
@@ -102,7 +125,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -110,16 +133,16 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 };
 
 /// This determines the average expected block time that we are targeting.
-/// Blocks will be produced at a minimum duration defined by `SLOT_DURATION`.
-/// `SLOT_DURATION` is picked up by `pallet_timestamp` which is in turn picked
-/// up by `pallet_aura` to implement `fn slot_duration()`.
+/// With proof of work there are no slots: `pallet_difficulty` retargets the difficulty
+/// after every block so that the average block time converges to this value.
 ///
 /// Change this to adjust the block time.
-pub const MILLISECS_PER_BLOCK: u64 = 6000;
+pub const MILLISECS_PER_BLOCK: u64 = 10_000;
 
-// NOTE: Currently it is not possible to change the slot duration after the chain has started.
-//       Attempting to do so will brick block production.
-pub const SLOT_DURATION: u64 = MILLISECS_PER_BLOCK;
+/// Minimum time between the timestamps of two consecutive blocks. PoW blocks arrive at
+/// random intervals and are sometimes found in quick succession, so this only has to keep
+/// timestamps strictly increasing.
+pub const MINIMUM_PERIOD: u64 = 1;
 
 // Time is measured by number of blocks.
 pub const MINUTES: BlockNumber = 60_000 / (MILLISECS_PER_BLOCK as BlockNumber);
@@ -214,17 +237,16 @@ impl frame_system::Config for Runtime {
 
 impl pallet_randomness_collective_flip::Config for Runtime {}
 
-impl pallet_aura::Config for Runtime {
-	type AuthorityId = AuraId;
-	type DisabledValidators = ();
-	type MaxAuthorities = ConstU32<32>;
+parameter_types! {
+	/// Equivocation reports are useless once the offender's bond could have been withdrawn.
+	pub const ReportLongevity: u64 = (BONDING_DURATION * SESSION_PERIOD) as u64;
 }
 
 impl pallet_grandpa::Config for Runtime {
 	type Event = Event;
 	type Call = Call;
 
-	type KeyOwnerProofSystem = ();
+	type KeyOwnerProofSystem = Historical;
 
 	type KeyOwnerProof =
 		<Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
@@ -234,17 +256,70 @@ impl pallet_grandpa::Config for Runtime {
 		GrandpaId,
 	)>>::IdentificationTuple;
 
-	type HandleEquivocation = ();
+	type HandleEquivocation =
+		pallet_grandpa::EquivocationHandler<Self::KeyOwnerIdentification, Offences, ReportLongevity>;
+
+	type WeightInfo = ();
+	type MaxAuthorities = ConstU32<MAX_VALIDATORS>;
+}
+
+/// Number of blocks in a session. The GRANDPA authority set can change once per session.
+pub const SESSION_PERIOD: BlockNumber = HOURS;
+
+/// Number of sessions a validator bond stays slashable after its validator left.
+pub const BONDING_DURATION: u32 = 24;
 
+/// Maximum number of GRANDPA voters.
+pub const MAX_VALIDATORS: u32 = 32;
+
+impl pallet_session::Config for Runtime {
+	type Event = Event;
+	type ValidatorId = AccountId;
+	type ValidatorIdOf = ConvertInto;
+	type ShouldEndSession = pallet_session::PeriodicSessions<ConstU32<SESSION_PERIOD>, ConstU32<0>>;
+	type NextSessionRotation =
+		pallet_session::PeriodicSessions<ConstU32<SESSION_PERIOD>, ConstU32<0>>;
+	type SessionManager = pallet_session::historical::NoteHistoricalRoot<Self, ValidatorSet>;
+	type SessionHandler = <opaque::SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = opaque::SessionKeys;
 	type WeightInfo = ();
-	type MaxAuthorities = ConstU32<32>;
+}
+
+impl pallet_session::historical::Config for Runtime {
+	type FullIdentification = Balance;
+	type FullIdentificationOf = pallet_validator_set::BondOf<Self>;
+}
+
+impl pallet_offences::Config for Runtime {
+	type Event = Event;
+	type IdentificationTuple = pallet_session::historical::IdentificationTuple<Self>;
+	type OnOffenceHandler = ValidatorSet;
+}
+
+/// Configure the GRANDPA validator set in pallets/validator-set.
+impl pallet_validator_set::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type ValidatorBond = ConstU128<{ 1_000 * DOLLARS }>;
+	type MaxValidators = ConstU32<MAX_VALIDATORS>;
+	type BondingDuration = ConstU32<BONDING_DURATION>;
+	type ValidatorRegistration = Session;
+	type Slash = ();
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	Call: From<C>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type OverarchingCall = Call;
 }
 
 impl pallet_timestamp::Config for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
 	type Moment = u64;
 	type OnTimestampSet = ();
-	type MinimumPeriod = ConstU64<{ SLOT_DURATION / 2 }>;
+	type MinimumPeriod = ConstU64<MINIMUM_PERIOD>;
 	type WeightInfo = ();
 }
 
@@ -263,7 +338,7 @@ impl pallet_balances::Config for Runtime {
 }
 
 impl pallet_transaction_payment::Config for Runtime {
-	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type OnChargeTransaction = CurrencyAdapter<Balances, pallet_rewards::DealWithFees<Runtime>>;
 	type OperationalFeeMultiplier = ConstU8<5>;
 	type WeightToFee = IdentityFee<Balance>;
 	type LengthToFee = IdentityFee<Balance>;
@@ -323,6 +398,67 @@ impl pallet_contracts::Config for Runtime {
 	type ContractAccessWeight = DefaultContractAccessWeight<BlockWeights>;
 }
 
+parameter_types! {
+	pub MinDifficulty: U256 = U256::from(1_000);
+}
+
+/// Configure the difficulty adjustment in pallets/difficulty.
+impl pallet_difficulty::Config for Runtime {
+	type TargetBlockTime = ConstU64<MILLISECS_PER_BLOCK>;
+	type DampFactor = ConstU64<3>;
+	type ClampFactor = ConstU64<2>;
+	type MinDifficulty = MinDifficulty;
+}
+
+/// Configure the mining zone parameters in pallets/geo-mining.
+impl pallet_geo_mining::Config for Runtime {
+	type Event = Event;
+	type ZoneOrigin = frame_system::EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const SubsidySchedule: pallet_rewards::SubsidySchedule<BlockNumber> =
+		pallet_rewards::SubsidySchedule::Halving { interval: 4 * 365 * DAYS };
+	pub AuthorFeeShare: Percent = Percent::from_percent(80);
+}
+
+/// Configure the block rewards in pallets/rewards.
+impl pallet_rewards::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	// The author recorded by the block author inherent, checked against the PoW pre-digest.
+	type FindAuthor = BlockAuthor;
+	type InitialSubsidy = ConstU128<{ 10 * DOLLARS }>;
+	type SubsidySchedule = SubsidySchedule;
+	type AuthorFeeShare = AuthorFeeShare;
+	// There is no treasury or geo-zone bonus pot yet, the remaining 20% of fees are burned.
+	type TreasuryFeeShare = ();
+	type Treasury = ();
+	type GeoBonusFeeShare = ();
+	type GeoBonus = ();
+}
+
+/// Configure the block author inherent in pallets/block-author.
+impl pallet_block_author::Config for Runtime {
+	// The inherent must name the author the PoW pre-digest claims.
+	type FindAuthor = pallet_rewards::PowAuthor<AccountId>;
+}
+
+/// Configure the location witness registry in pallets/location-witness.
+impl pallet_location_witness::Config for Runtime {
+	type Event = Event;
+	type WitnessOrigin = frame_system::EnsureRoot<AccountId>;
+	type MaxWitnesses = ConstU32<256>;
+}
+
+/// Configure the regional mining quotas in pallets/geo-quotas.
+impl pallet_geo_quotas::Config for Runtime {
+	type Event = Event;
+	type RegionOrigin = frame_system::EnsureRoot<AccountId>;
+	type MaxRegions = ConstU32<256>;
+	type QuotaWindow = ConstU32<HOURS>;
+}
+
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type Event = Event;
@@ -338,14 +474,26 @@ construct_runtime!(
 		System: frame_system,
 		RandomnessCollectiveFlip: pallet_randomness_collective_flip,
 		Timestamp: pallet_timestamp,
-		Aura: pallet_aura,
-		Grandpa: pallet_grandpa,
+		BlockAuthor: pallet_block_author,
+		Rewards: pallet_rewards,
+		// Genesis is built in this order. The endowments must exist before the validator set
+		// reserves the genesis bonds from them, and the validator set before the session
+		// pallet asks it for the first validators, which in turn sets the GRANDPA authorities.
 		Balances: pallet_balances,
+		ValidatorSet: pallet_validator_set,
+		Session: pallet_session,
+		Historical: pallet_session_historical::{Pallet},
+		Offences: pallet_offences,
+		Grandpa: pallet_grandpa,
 		TransactionPayment: pallet_transaction_payment,
 		Sudo: pallet_sudo,
 		// Include the custom logic from the pallet-template in the runtime.
 		TemplateModule: pallet_template,
 		Contracts: pallet_contracts,
+		Difficulty: pallet_difficulty,
+		GeoMining: pallet_geo_mining,
+		LocationWitness: pallet_location_witness,
+		GeoQuotas: pallet_geo_quotas,
 	}
 );
 
@@ -452,13 +600,37 @@ impl_runtime_apis! {
 		}
 	}
 
-	impl sp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
-		fn slot_duration() -> sp_consensus_aura::SlotDuration {
-			sp_consensus_aura::SlotDuration::from_millis(Aura::slot_duration())
+	impl sp_consensus_pow::DifficultyApi<Block, U256> for Runtime {
+		fn difficulty() -> U256 {
+			Difficulty::difficulty()
+		}
+	}
+
+	impl geopow_runtime_api::GeoMiningApi<Block> for Runtime {
+		fn zone_params() -> geopow_runtime_api::MiningZoneParams {
+			GeoMining::zone_params()
+		}
+
+		fn is_eligible(parent: Hash, location: geopow_runtime_api::GeoPoint) -> bool {
+			GeoMining::is_eligible(parent, location)
+		}
+	}
+
+	impl geopow_runtime_api::LocationWitnessApi<Block, AccountId> for Runtime {
+		fn attestation_context() -> geopow_runtime_api::AttestationContext<AccountId> {
+			LocationWitness::attestation_context()
+		}
+	}
+
+	impl geopow_runtime_api::GeoQuotasApi<Block> for Runtime {
+		fn region_at(
+			location: geopow_runtime_api::GeoPoint,
+		) -> Option<geopow_runtime_api::RegionId> {
+			GeoQuotas::region_at(location)
 		}
 
-		fn authorities() -> Vec<AuraId> {
-			Aura::authorities().into_inner()
+		fn has_quota(region: geopow_runtime_api::RegionId) -> bool {
+			GeoQuotas::has_quota(region)
 		}
 	}
 
@@ -484,23 +656,26 @@ impl_runtime_apis! {
 		}
 
 		fn submit_report_equivocation_unsigned_extrinsic(
-			_equivocation_proof: fg_primitives::EquivocationProof<
+			equivocation_proof: fg_primitives::EquivocationProof<
 				<Block as BlockT>::Hash,
 				NumberFor<Block>,
 			>,
-			_key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
+			key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
 		) -> Option<()> {
-			None
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Grandpa::submit_unsigned_equivocation_report(equivocation_proof, key_owner_proof)
 		}
 
 		fn generate_key_ownership_proof(
 			_set_id: fg_primitives::SetId,
-			_authority_id: GrandpaId,
+			authority_id: GrandpaId,
 		) -> Option<fg_primitives::OpaqueKeyOwnershipProof> {
-			// NOTE: this is the only implementation possible since we've
-			// defined our key owner proof type as a bottom type (i.e. a type
-			// with no values).
-			None
+			use codec::Encode;
+
+			Historical::prove((fg_primitives::KEY_TYPE, authority_id))
+				.map(|p| p.encode())
+				.map(fg_primitives::OpaqueKeyOwnershipProof::new)
 		}
 	}
 